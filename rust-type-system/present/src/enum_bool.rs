@@ -1,133 +1,1208 @@
+pub mod backend;
+pub mod cache;
+pub mod clock;
+pub mod coalesce;
+pub mod file_cache;
+pub mod http;
+pub mod json;
+pub mod metrics;
+pub mod replay;
+pub mod retry;
+
 mod bad {
+    use super::backend::{Data, DataCache, QueryError, RestService};
 
-    struct DataService {
-        cache: DataCache,
-        rest_api: RestService,
+    struct DataService<C, R> {
+        cache: C,
+        rest_api: R,
     }
 
-    impl DataService {
+    impl<C: DataCache, R: RestService> DataService<C, R> {
         /// Query the data ...
         ///
-        /// * `use_cache`: If enable only cache will be used, 
+        /// * `use_cache`: If enable only cache will be used,
         ///   otherwise send REST request.
         /// * `ignore_empty`: Ignore any empty data query.
-        fn query_data(&self, use_cache: bool, ignore_empty: bool) {
+        fn query_data(&self, use_cache: bool, ignore_empty: bool) -> Result<Vec<Data>, QueryError> {
             let mut data = if use_cache {
-                self.cache.query(...)
-            }else {
-                self.rest_api.query(...)
+                self.cache.query("/data")?
+            } else {
+                self.rest_api.query("/data")?
             };
 
             if ignore_empty {
-                data.retain(...)
+                data.retain(|item| !item.is_empty())
             }
-            
-            data
+
+            Ok(data)
         }
     }
 
-    fn example() {
+    fn example<C: DataCache, R: RestService>(service: &DataService<C, R>) {
         let data = service.query_data(true, false);
     }
-
 }
 
 mod refactoring {
-    
-    impl DataService {
+    use super::backend::{Data, DataCache, QueryError, RestService};
+
+    struct DataService<C, R> {
+        cache: C,
+        rest_api: R,
+    }
+
+    impl<C: DataCache, R: RestService> DataService<C, R> {
         /// Query the data ...
         ///
-        /// * `use_api`: If enable API service will be used, 
-        ///   otherwise send REST request.
+        /// * `use_api`: If enable API service will be used,
+        ///   otherwise cache will be used.
         /// * `ignore_empty`: Ignore any empty data query.
-        fn query_data(&self, use_api: bool, ignore_empty: bool) {
-            let mut data = if use_cache {
-                self.cache.query(...)
-            }else {
-                self.rest_api.query(...)
+        fn query_data(&self, use_api: bool, ignore_empty: bool) -> Result<Vec<Data>, QueryError> {
+            let mut data = if use_api {
+                self.rest_api.query("/data")?
+            } else {
+                self.cache.query("/data")?
             };
 
             if ignore_empty {
-                data.retain(...)
+                data.retain(|item| !item.is_empty())
             }
-            
-            data
+
+            Ok(data)
         }
     }
 
-    fn example() {
+    fn example<C: DataCache, R: RestService>(service: &DataService<C, R>) {
         // No Errors!
         let data = service.query_data(true, false);
     }
-
 }
 
-
 mod refactoring_2 {
+    use super::backend::{Data, DataCache, QueryError, RestService};
 
-    struct DataService {
-        cache: DataCache,
-        rest_api: RestService,
+    struct DataService<C, R> {
+        cache: C,
+        rest_api: R,
     }
 
-    impl DataService {
+    impl<C: DataCache, R: RestService> DataService<C, R> {
         /// Query the data ...
         ///
         /// * `ignore_empty`: Ignore any empty data query.
-        /// * `use_cache`: If enable only cache will be used, 
+        /// * `use_cache`: If enable only cache will be used,
         ///   otherwise send REST request.
-        fn query_data(&self, ignore_empty: bool, use_cache: bool) {
+        fn query_data(&self, ignore_empty: bool, use_cache: bool) -> Result<Vec<Data>, QueryError> {
             let mut data = if use_cache {
-                self.cache.query(...)
-            }else {
-                self.rest_api.query(...)
+                self.cache.query("/data")?
+            } else {
+                self.rest_api.query("/data")?
             };
 
             if ignore_empty {
-                data.retain(...)
+                data.retain(|item| !item.is_empty())
             }
-            
-            data
+
+            Ok(data)
         }
     }
 
-    fn example() {
+    fn example<C: DataCache, R: RestService>(service: &DataService<C, R>) {
         // No Errors!
         let data = service.query_data(true, false);
     }
-
 }
 
-mod good {
+pub mod good {
+    use std::{
+        collections::HashSet,
+        fmt,
+        time::{Duration, Instant},
+    };
+
+    use super::{
+        backend::{Data, DataCache, DataSourceBackend, QueryError, RestService},
+        coalesce::Coalescer,
+        metrics::Metrics,
+    };
 
-    enum DataSource {
+    /// Key of the data in the cache and path of the REST request.
+    pub const DATA_KEY: &str = "/data";
+
+    /// Number of the first page of paginated data.
+    pub const FIRST_PAGE: usize = 1;
+
+    /// Key of a page of the data under the key, starting at [`FIRST_PAGE`].
+    pub fn page_key(key: &str, page: usize) -> String {
+        format!("{key}?page={page}")
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    pub enum DataSource {
+        Cache,
+        ApiService,
+        /// Use the cache and fall back to the API on a miss or expiry,
+        /// storing the API data in the cache.
+        CacheOrApi,
+        /// Use the API and fall back to the cache when it fails, storing
+        /// the API data in the cache.
+        ApiThenCache,
+        /// Query the API and write the data through to the cache.
+        Refresh,
+    }
+
+    /// Backend which answered a query.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    pub enum ServedBy {
         Cache,
         ApiService,
     }
 
-    enum EmptyDataOptions {
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct QueryResult {
+        pub data: Vec<Data>,
+        pub served_by: ServedBy,
+        /// Error of the preferred backend when the data is served by the
+        /// fallback one. Cached data may be stale in that case.
+        pub fallback_reason: Option<QueryError>,
+    }
+
+    impl QueryResult {
+        fn new(data: Vec<Data>, served_by: ServedBy) -> Self {
+            Self {
+                data,
+                served_by,
+                fallback_reason: None,
+            }
+        }
+    }
+
+    /// Filtering of the queried data, carrying the parameters of each mode
+    /// instead of adding a boolean flag for each of them.
+    pub enum EmptyDataOptions {
+        /// Remove items without value.
         Ignore,
         Keep,
+        /// Keep items with values of at least the given count of characters.
+        MinLength(usize),
+        /// Keep items matching the predicate.
+        Custom(Box<dyn Fn(&Data) -> bool>),
+        /// Keep the first item of each id only.
+        Dedupe,
+        /// Apply all options in order.
+        Chain(Vec<EmptyDataOptions>),
+    }
+
+    impl EmptyDataOptions {
+        /// Combines the options to apply the `next` ones after these.
+        pub fn then(self, next: EmptyDataOptions) -> Self {
+            match self {
+                EmptyDataOptions::Chain(mut options) => {
+                    options.push(next);
+                    EmptyDataOptions::Chain(options)
+                }
+                options => EmptyDataOptions::Chain(vec![options, next]),
+            }
+        }
+
+        /// Creates the filter to apply these options on queried items.
+        pub fn filter(&self) -> DataFilter<'_> {
+            let mut steps = Vec::new();
+            self.collect_steps(&mut steps);
+            DataFilter { steps }
+        }
+
+        fn collect_steps<'a>(&'a self, steps: &mut Vec<FilterStep<'a>>) {
+            match self {
+                EmptyDataOptions::Keep => {}
+                EmptyDataOptions::Ignore
+                | EmptyDataOptions::MinLength(_)
+                | EmptyDataOptions::Custom(_) => steps.push(FilterStep::Check(self)),
+                EmptyDataOptions::Dedupe => steps.push(FilterStep::Dedupe(HashSet::new())),
+                EmptyDataOptions::Chain(options) => options
+                    .iter()
+                    .for_each(|options| options.collect_steps(steps)),
+            }
+        }
+
+        /// Checks the item against the options which don't keep state
+        /// between items, passing it through deduplication.
+        fn accepts(&self, item: &Data) -> bool {
+            match self {
+                EmptyDataOptions::Ignore => !item.is_empty(),
+                EmptyDataOptions::Keep | EmptyDataOptions::Dedupe => true,
+                EmptyDataOptions::MinLength(length) => item.value.chars().count() >= *length,
+                EmptyDataOptions::Custom(predicate) => predicate(item),
+                EmptyDataOptions::Chain(options) => {
+                    options.iter().all(|options| options.accepts(item))
+                }
+            }
+        }
+
+        fn dedupes(&self) -> bool {
+            match self {
+                EmptyDataOptions::Dedupe => true,
+                EmptyDataOptions::Chain(options) => options.iter().any(EmptyDataOptions::dedupes),
+                _ => false,
+            }
+        }
+    }
+
+    impl fmt::Debug for EmptyDataOptions {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match self {
+                EmptyDataOptions::Ignore => f.write_str("Ignore"),
+                EmptyDataOptions::Keep => f.write_str("Keep"),
+                EmptyDataOptions::MinLength(length) => {
+                    f.debug_tuple("MinLength").field(length).finish()
+                }
+                EmptyDataOptions::Custom(_) => f.write_str("Custom(..)"),
+                EmptyDataOptions::Dedupe => f.write_str("Dedupe"),
+                EmptyDataOptions::Chain(options) => f.debug_tuple("Chain").field(options).finish(),
+            }
+        }
+    }
+
+    enum FilterStep<'a> {
+        /// Options without state between items.
+        Check(&'a EmptyDataOptions),
+        /// Ids of the items which passed this step already.
+        Dedupe(HashSet<String>),
+    }
+
+    /// Filter created from [`EmptyDataOptions`] for one query.
+    ///
+    /// It keeps the state needed to deduplicate items, so items can
+    /// be filtered one by one.
+    pub struct DataFilter<'a> {
+        steps: Vec<FilterStep<'a>>,
+    }
+
+    impl DataFilter<'_> {
+        /// Checks if the item passes all steps of the filter.
+        pub fn keep(&mut self, item: &Data) -> bool {
+            self.steps.iter_mut().all(|step| match step {
+                FilterStep::Check(options) => options.accepts(item),
+                FilterStep::Dedupe(seen) => seen.insert(item.id.clone()),
+            })
+        }
+    }
+
+    /// Order of the items returned by a query.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum SortOrder {
+        IdAscending,
+        IdDescending,
+        ValueAscending,
+        ValueDescending,
     }
 
-    impl DataService {
-        fn query_data(&self, source: DataSource, empty: EmptyDataOptions) {
-            let mut data = match source {
-                DataSource::Cache => self.cache.query(...),
-                DataSource::ApiService => self.rest_api.query(...),
+    impl SortOrder {
+        fn sort(self, data: &mut [Data]) {
+            match self {
+                SortOrder::IdAscending => data.sort_by(|a, b| a.id.cmp(&b.id)),
+                SortOrder::IdDescending => data.sort_by(|a, b| b.id.cmp(&a.id)),
+                SortOrder::ValueAscending => data.sort_by(|a, b| a.value.cmp(&b.value)),
+                SortOrder::ValueDescending => data.sort_by(|a, b| b.value.cmp(&a.value)),
+            }
+        }
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub enum OptionsError {
+        /// A limit of zero items would never return any data.
+        ZeroLimit,
+        /// A zero timeout would fail every request.
+        ZeroTimeout,
+        /// Sorting needs all items in memory, so streams can't do it.
+        SortNotStreamable,
+        /// Deduplication needs the ids of all items in memory, so streams
+        /// can't do it.
+        DedupeNotStreamable,
+        /// The source stores every fetched page in the cache, which could
+        /// keep the whole result in memory.
+        SourceNotStreamable(DataSource),
+    }
+
+    impl fmt::Display for OptionsError {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match self {
+                OptionsError::ZeroLimit => f.write_str("Limit must be at least 1"),
+                OptionsError::ZeroTimeout => f.write_str("Timeout must be greater than zero"),
+                OptionsError::SortNotStreamable => f.write_str("Streamed data can't be sorted"),
+                OptionsError::DedupeNotStreamable => {
+                    f.write_str("Streamed data can't be deduplicated")
+                }
+                OptionsError::SourceNotStreamable(source) => {
+                    write!(f, "Data can't be streamed from {source:?}")
+                }
+            }
+        }
+    }
+
+    impl std::error::Error for OptionsError {}
+
+    /// Builder of the options of [`DataService::query_data`], so new features
+    /// don't add positional parameters to it.
+    ///
+    /// By default the cache is used with the API as fallback, and all items
+    /// are kept unsorted and without limit. Queries only take options checked
+    /// by [`build`](Self::build).
+    #[derive(Debug)]
+    pub struct QueryOptions {
+        source: DataSource,
+        empty: EmptyDataOptions,
+        limit: Option<usize>,
+        offset: usize,
+        sort: Option<SortOrder>,
+        timeout: Option<Duration>,
+    }
+
+    impl Default for QueryOptions {
+        fn default() -> Self {
+            Self {
+                source: DataSource::CacheOrApi,
+                empty: EmptyDataOptions::Keep,
+                limit: None,
+                offset: 0,
+                sort: None,
+                timeout: None,
+            }
+        }
+    }
+
+    impl QueryOptions {
+        pub fn source(mut self, source: DataSource) -> Self {
+            self.source = source;
+            self
+        }
+
+        pub fn empty(mut self, empty: EmptyDataOptions) -> Self {
+            self.empty = empty;
+            self
+        }
+
+        /// Returns at most `limit` items.
+        pub fn limit(mut self, limit: usize) -> Self {
+            self.limit = Some(limit);
+            self
+        }
+
+        /// Skips the first `offset` items, after filtering and sorting.
+        pub fn offset(mut self, offset: usize) -> Self {
+            self.offset = offset;
+            self
+        }
+
+        pub fn sort(mut self, sort: SortOrder) -> Self {
+            self.sort = Some(sort);
+            self
+        }
+
+        /// Overrides the timeout of the backends supporting it.
+        pub fn timeout(mut self, timeout: Duration) -> Self {
+            self.timeout = Some(timeout);
+            self
+        }
+
+        /// Checks the options, so invalid ones are rejected where they are
+        /// built instead of when querying.
+        pub fn build(self) -> Result<ValidQueryOptions, OptionsError> {
+            if self.limit == Some(0) {
+                return Err(OptionsError::ZeroLimit);
+            }
+            if self.timeout == Some(Duration::ZERO) {
+                return Err(OptionsError::ZeroTimeout);
+            }
+            Ok(ValidQueryOptions(self))
+        }
+    }
+
+    /// Query options which passed [`QueryOptions::build`], the only way
+    /// to create them.
+    #[derive(Debug)]
+    pub struct ValidQueryOptions(QueryOptions);
+
+    /// Fetches are only shared with the same timeout, so no caller waits
+    /// longer than its own timeout or fails because of another one.
+    type FetchKey = (DataSource, String, Option<Duration>);
+
+    pub struct DataService<C, R> {
+        cache: C,
+        rest_api: R,
+        metrics: Metrics,
+        /// Fetches running for each source, key and timeout.
+        in_flight: Coalescer<FetchKey, Result<QueryResult, QueryError>>,
+    }
+
+    impl<C: DataCache, R: RestService> DataService<C, R> {
+        pub fn new(cache: C, rest_api: R) -> Self {
+            Self {
+                cache,
+                rest_api,
+                metrics: Metrics::default(),
+                in_flight: Coalescer::default(),
+            }
+        }
+
+        /// Metrics of the queries since the service was created.
+        pub fn metrics(&self) -> &Metrics {
+            &self.metrics
+        }
+
+        pub fn query_data(&self, options: ValidQueryOptions) -> Result<QueryResult, QueryError> {
+            let ValidQueryOptions(options) = options;
+            let mut result = self.fetch_shared(options.source, DATA_KEY, options.timeout)?;
+
+            let mut filter = options.empty.filter();
+            let count = result.data.len();
+            result.data.retain(|item| filter.keep(item));
+            self.metrics.record_filtered(count - result.data.len());
+            if let Some(sort) = options.sort {
+                sort.sort(&mut result.data);
+            }
+            result.data.drain(..options.offset.min(result.data.len()));
+            if let Some(limit) = options.limit {
+                result.data.truncate(limit);
+            }
+
+            Ok(result)
+        }
+
+        /// Streams the items page by page, so only one page is kept in memory
+        /// however many items there are.
+        ///
+        /// Pages are fetched lazily from the keys built by [`page_key`] until
+        /// an empty or missing page. Filtering, offset and limit are applied
+        /// on the fly, while sorting and deduplication aren't supported. The
+        /// stream ends after yielding an error.
+        ///
+        /// Only [`DataSource::Cache`] and [`DataSource::ApiService`] are
+        /// supported, since the other sources store the fetched pages in the
+        /// cache, which keeps all of them in memory with an unbounded cache.
+        pub fn query_stream(&self, options: ValidQueryOptions) -> DataStream<'_, C, R> {
+            let ValidQueryOptions(options) = options;
+            let error = match options.source {
+                DataSource::Cache | DataSource::ApiService => Ok(()),
+                source @ (DataSource::CacheOrApi
+                | DataSource::ApiThenCache
+                | DataSource::Refresh) => Err(OptionsError::SourceNotStreamable(source)),
+            }
+            .and(match options.sort {
+                Some(_) => Err(OptionsError::SortNotStreamable),
+                None => Ok(()),
+            })
+            .and(if options.empty.dedupes() {
+                Err(OptionsError::DedupeNotStreamable)
+            } else {
+                Ok(())
+            })
+            .err()
+            .map(QueryError::InvalidOptions);
+
+            DataStream {
+                service: self,
+                next_page: error.is_none().then_some(FIRST_PAGE),
+                error,
+                page: Vec::new().into_iter(),
+                skip: options.offset,
+                remaining: options.limit,
+                options,
+            }
+        }
+
+        /// Fetches the data, sharing the fetch with concurrent queries of the
+        /// same source, key and timeout whatever their filters.
+        fn fetch_shared(
+            &self,
+            source: DataSource,
+            key: &str,
+            timeout: Option<Duration>,
+        ) -> Result<QueryResult, QueryError> {
+            let start = Instant::now();
+            let result = self.in_flight.run(
+                (source, key.to_owned(), timeout),
+                || self.metrics.record_coalesced(),
+                || self.fetch(source, key, timeout),
+            );
+            self.metrics
+                .record_query(source, start.elapsed(), result.is_err());
+            result
+        }
+
+        fn fetch(
+            &self,
+            source: DataSource,
+            key: &str,
+            timeout: Option<Duration>,
+        ) -> Result<QueryResult, QueryError> {
+            match source {
+                DataSource::Cache => {
+                    let data = self.query_cache(key)?;
+                    Ok(QueryResult::new(data, ServedBy::Cache))
+                }
+                DataSource::ApiService => {
+                    let data = query(&self.rest_api, key, timeout)?;
+                    Ok(QueryResult::new(data, ServedBy::ApiService))
+                }
+                DataSource::CacheOrApi => match self.query_cache(key) {
+                    Ok(data) => Ok(QueryResult::new(data, ServedBy::Cache)),
+                    Err(err) => {
+                        let mut result = self.fetch(DataSource::Refresh, key, timeout)?;
+                        result.fallback_reason = Some(err);
+                        Ok(result)
+                    }
+                },
+                DataSource::ApiThenCache => match self.fetch(DataSource::Refresh, key, timeout) {
+                    Ok(result) => Ok(result),
+                    Err(api_err) => {
+                        // API error is more relevant to callers than the cache miss.
+                        let data = self.query_cache(key).map_err(|_| api_err.clone())?;
+                        Ok(QueryResult {
+                            data,
+                            served_by: ServedBy::Cache,
+                            fallback_reason: Some(api_err),
+                        })
+                    }
+                },
+                DataSource::Refresh => {
+                    let data = query(&self.rest_api, key, timeout)?;
+                    self.cache.store(key, data.clone());
+                    Ok(QueryResult::new(data, ServedBy::ApiService))
+                }
+            }
+        }
+
+        fn query_cache(&self, key: &str) -> Result<Vec<Data>, QueryError> {
+            let result = self.cache.query(key);
+            self.metrics.record_cache(result.is_ok());
+            result
+        }
+    }
+
+    /// Lazy stream of the items of a query, see [`DataService::query_stream`].
+    pub struct DataStream<'a, C, R> {
+        service: &'a DataService<C, R>,
+        options: QueryOptions,
+        /// Items of the current page which aren't yielded yet.
+        page: std::vec::IntoIter<Data>,
+        /// Page to fetch once the current one is done, if any.
+        next_page: Option<usize>,
+        /// Error to yield before ending the stream.
+        error: Option<QueryError>,
+        skip: usize,
+        remaining: Option<usize>,
+    }
+
+    impl<C: DataCache, R: RestService> DataStream<'_, C, R> {
+        /// Fetches the next page, returning `false` once there are no more.
+        fn fetch_page(&mut self) -> bool {
+            let Some(page) = self.next_page.take() else {
+                return false;
             };
 
-            match empty {
-                EmptyDataOptions::Ignore => data.retain(...),
-                EmptyDataOptions::Keep => {},
+            let key = page_key(DATA_KEY, page);
+            let options = &self.options;
+            match self
+                .service
+                .fetch_shared(options.source, &key, options.timeout)
+            {
+                Ok(result) if result.data.is_empty() => false,
+                Ok(result) => {
+                    self.page = result.data.into_iter();
+                    self.next_page = Some(page + 1);
+                    true
+                }
+                // Only the first page is required to exist.
+                Err(QueryError::NotFound(_)) if page > FIRST_PAGE => false,
+                Err(err) => {
+                    self.error = Some(err);
+                    false
+                }
+            }
+        }
+    }
+
+    impl<C: DataCache, R: RestService> Iterator for DataStream<'_, C, R> {
+        type Item = Result<Data, QueryError>;
+
+        fn next(&mut self) -> Option<Self::Item> {
+            loop {
+                if let Some(err) = self.error.take() {
+                    return Some(Err(err));
+                }
+                if self.remaining == Some(0) {
+                    return None;
+                }
+
+                let Some(item) = self.page.next() else {
+                    if self.fetch_page() {
+                        continue;
+                    }
+                    return self.error.take().map(Err);
+                };
+
+                // Only filters without state between items are streamable.
+                if !self.options.empty.accepts(&item) {
+                    self.service.metrics.record_filtered(1);
+                } else if self.skip > 0 {
+                    self.skip -= 1;
+                } else {
+                    if let Some(remaining) = &mut self.remaining {
+                        *remaining -= 1;
+                    }
+                    return Some(Ok(item));
+                }
             }
-            
-            data
         }
     }
 
-    fn example() {
-        let data = service.query_data(DataSource::Cache, EmptyDataOptions::Keep);
+    fn query<B: DataSourceBackend>(
+        backend: &B,
+        key: &str,
+        timeout: Option<Duration>,
+    ) -> Result<Vec<Data>, QueryError> {
+        match timeout {
+            Some(timeout) => backend.query_with_timeout(key, timeout),
+            None => backend.query(key),
+        }
+    }
+
+    fn example<C: DataCache, R: RestService>(
+        service: &DataService<C, R>,
+    ) -> Result<(), OptionsError> {
+        let options = QueryOptions::default()
+            .source(DataSource::Cache)
+            .empty(EmptyDataOptions::Ignore)
+            .limit(100)
+            .build()?;
+        let data = service.query_data(options);
+        Ok(())
     }
 
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::enum_bool::{
+            backend::{DataSourceBackend, InMemoryRestService},
+            cache::InMemoryCache,
+        };
+
+        fn items(prefix: &str) -> Vec<Data> {
+            vec![
+                Data::new(format!("{prefix}-1"), "first"),
+                Data::new(format!("{prefix}-2"), ""),
+                Data::new(format!("{prefix}-3"), "third"),
+            ]
+        }
+
+        fn service() -> DataService<InMemoryCache, InMemoryRestService> {
+            let cache = InMemoryCache::new();
+            cache.store(DATA_KEY, items("cache"));
+            let rest_api = InMemoryRestService::new().respond(DATA_KEY, Ok(items("api")));
+
+            DataService::new(cache, rest_api)
+        }
+
+        fn options(source: DataSource, empty: EmptyDataOptions) -> QueryOptions {
+            QueryOptions::default().source(source).empty(empty)
+        }
+
+        fn ids(data: &[Data]) -> Vec<&str> {
+            data.iter().map(|item| item.id.as_str()).collect()
+        }
+
+        #[test]
+        fn cache_ignore_empty() {
+            let result = service()
+                .query_data(
+                    options(DataSource::Cache, EmptyDataOptions::Ignore)
+                        .build()
+                        .unwrap(),
+                )
+                .unwrap();
+            assert_eq!(ids(&result.data), ["cache-1", "cache-3"]);
+        }
+
+        #[test]
+        fn cache_keep_empty() {
+            let result = service()
+                .query_data(
+                    options(DataSource::Cache, EmptyDataOptions::Keep)
+                        .build()
+                        .unwrap(),
+                )
+                .unwrap();
+            assert_eq!(ids(&result.data), ["cache-1", "cache-2", "cache-3"]);
+        }
+
+        #[test]
+        fn api_ignore_empty() {
+            let result = service()
+                .query_data(
+                    options(DataSource::ApiService, EmptyDataOptions::Ignore)
+                        .build()
+                        .unwrap(),
+                )
+                .unwrap();
+            assert_eq!(ids(&result.data), ["api-1", "api-3"]);
+        }
+
+        #[test]
+        fn api_keep_empty() {
+            let result = service()
+                .query_data(
+                    options(DataSource::ApiService, EmptyDataOptions::Keep)
+                        .build()
+                        .unwrap(),
+                )
+                .unwrap();
+            assert_eq!(ids(&result.data), ["api-1", "api-2", "api-3"]);
+        }
+
+        #[test]
+        fn errors() {
+            let rest_api = InMemoryRestService::new()
+                .respond(DATA_KEY, Err(QueryError::Backend("Unavailable".into())));
+            let service = DataService::new(InMemoryCache::new(), rest_api);
+
+            assert_eq!(
+                service.query_data(
+                    options(DataSource::Cache, EmptyDataOptions::Keep)
+                        .build()
+                        .unwrap()
+                ),
+                Err(QueryError::NotFound(DATA_KEY.into()))
+            );
+            assert_eq!(
+                service.query_data(
+                    options(DataSource::ApiService, EmptyDataOptions::Ignore)
+                        .build()
+                        .unwrap()
+                ),
+                Err(QueryError::Backend("Unavailable".into()))
+            );
+        }
+
+        fn unavailable() -> QueryError {
+            QueryError::Backend("Unavailable".into())
+        }
+
+        #[test]
+        fn data_carrying_options() {
+            let rest_api = InMemoryRestService::new().respond(
+                DATA_KEY,
+                Ok(vec![
+                    Data::new("a", "long value"),
+                    Data::new("b", "short"),
+                    Data::new("a", "duplicate"),
+                    Data::new("c", ""),
+                    Data::new("d", "skip me"),
+                ]),
+            );
+            let service = DataService::new(InMemoryCache::new(), rest_api);
+            let query = |empty| {
+                let result = service
+                    .query_data(options(DataSource::ApiService, empty).build().unwrap())
+                    .unwrap();
+                result
+                    .data
+                    .into_iter()
+                    .map(|item| item.value)
+                    .collect::<Vec<_>>()
+            };
+
+            assert_eq!(
+                query(EmptyDataOptions::MinLength(6)),
+                ["long value", "duplicate", "skip me"]
+            );
+            assert_eq!(
+                query(EmptyDataOptions::Dedupe),
+                ["long value", "short", "", "skip me"]
+            );
+            assert_eq!(
+                query(EmptyDataOptions::Custom(Box::new(|item| item.id != "d"))),
+                ["long value", "short", "duplicate", ""]
+            );
+
+            // Dedupe only considers the items passing the filters before it.
+            let chain = EmptyDataOptions::MinLength(6)
+                .then(EmptyDataOptions::Custom(Box::new(|item| item.id != "a")))
+                .then(EmptyDataOptions::Dedupe);
+            assert_eq!(query(chain), ["skip me"]);
+
+            let chain = EmptyDataOptions::Ignore
+                .then(EmptyDataOptions::Dedupe)
+                .then(EmptyDataOptions::Dedupe);
+            assert_eq!(query(chain), ["long value", "short", "skip me"]);
+        }
+
+        #[test]
+        fn cache_or_api() {
+            let service = service();
+            let result = service
+                .query_data(
+                    options(DataSource::CacheOrApi, EmptyDataOptions::Keep)
+                        .build()
+                        .unwrap(),
+                )
+                .unwrap();
+            assert_eq!(result.served_by, ServedBy::Cache);
+            assert_eq!(result.fallback_reason, None);
+
+            service.cache.invalidate(DATA_KEY);
+            let result = service
+                .query_data(
+                    options(DataSource::CacheOrApi, EmptyDataOptions::Ignore)
+                        .build()
+                        .unwrap(),
+                )
+                .unwrap();
+            assert_eq!(result.served_by, ServedBy::ApiService);
+            assert_eq!(
+                result.fallback_reason,
+                Some(QueryError::NotFound(DATA_KEY.into()))
+            );
+            assert_eq!(ids(&result.data), ["api-1", "api-3"]);
+
+            // API data is stored in the cache without filtering.
+            assert_eq!(service.cache.query(DATA_KEY), Ok(items("api")));
+        }
+
+        #[test]
+        fn cache_or_api_errors() {
+            let rest_api = InMemoryRestService::new().respond(DATA_KEY, Err(unavailable()));
+            let service = DataService::new(InMemoryCache::new(), rest_api);
+
+            assert_eq!(
+                service.query_data(
+                    options(DataSource::CacheOrApi, EmptyDataOptions::Keep)
+                        .build()
+                        .unwrap()
+                ),
+                Err(unavailable())
+            );
+        }
+
+        #[test]
+        fn api_then_cache() {
+            let service = service();
+            let result = service
+                .query_data(
+                    options(DataSource::ApiThenCache, EmptyDataOptions::Keep)
+                        .build()
+                        .unwrap(),
+                )
+                .unwrap();
+            assert_eq!(result.served_by, ServedBy::ApiService);
+            assert_eq!(service.cache.query(DATA_KEY), Ok(items("api")));
+
+            let cache = InMemoryCache::new();
+            cache.store(DATA_KEY, items("cache"));
+            let rest_api = InMemoryRestService::new().respond(DATA_KEY, Err(unavailable()));
+            let service = DataService::new(cache, rest_api);
+
+            let result = service
+                .query_data(
+                    options(DataSource::ApiThenCache, EmptyDataOptions::Ignore)
+                        .build()
+                        .unwrap(),
+                )
+                .unwrap();
+            assert_eq!(result.served_by, ServedBy::Cache);
+            assert_eq!(result.fallback_reason, Some(unavailable()));
+            assert_eq!(ids(&result.data), ["cache-1", "cache-3"]);
+
+            service.cache.invalidate(DATA_KEY);
+            assert_eq!(
+                service.query_data(
+                    options(DataSource::ApiThenCache, EmptyDataOptions::Keep)
+                        .build()
+                        .unwrap()
+                ),
+                Err(unavailable())
+            );
+        }
+
+        #[test]
+        fn refresh() {
+            let service = service();
+            let result = service
+                .query_data(
+                    options(DataSource::Refresh, EmptyDataOptions::Keep)
+                        .build()
+                        .unwrap(),
+                )
+                .unwrap();
+            assert_eq!(result.served_by, ServedBy::ApiService);
+            assert_eq!(ids(&result.data), ["api-1", "api-2", "api-3"]);
+            assert_eq!(service.cache.query(DATA_KEY), Ok(items("api")));
+
+            let cache = InMemoryCache::new();
+            cache.store(DATA_KEY, items("cache"));
+            let rest_api = InMemoryRestService::new().respond(DATA_KEY, Err(unavailable()));
+            let service = DataService::new(cache, rest_api);
+
+            assert_eq!(
+                service.query_data(
+                    options(DataSource::Refresh, EmptyDataOptions::Keep)
+                        .build()
+                        .unwrap()
+                ),
+                Err(unavailable())
+            );
+            assert_eq!(service.cache.query(DATA_KEY), Ok(items("cache")));
+        }
+
+        #[test]
+        fn default_options() {
+            let result = service()
+                .query_data(QueryOptions::default().build().unwrap())
+                .unwrap();
+            assert_eq!(result.served_by, ServedBy::Cache);
+            assert_eq!(ids(&result.data), ["cache-1", "cache-2", "cache-3"]);
+        }
+
+        #[test]
+        fn limit_and_offset() {
+            let service = service();
+            let result = service
+                .query_data(QueryOptions::default().limit(2).build().unwrap())
+                .unwrap();
+            assert_eq!(ids(&result.data), ["cache-1", "cache-2"]);
+
+            let result = service
+                .query_data(QueryOptions::default().offset(1).build().unwrap())
+                .unwrap();
+            assert_eq!(ids(&result.data), ["cache-2", "cache-3"]);
+
+            let result = service
+                .query_data(QueryOptions::default().offset(5).build().unwrap())
+                .unwrap();
+            assert!(result.data.is_empty());
+
+            // The offset applies to the filtered items.
+            let options = options(DataSource::Cache, EmptyDataOptions::Ignore)
+                .offset(1)
+                .limit(5);
+            let result = service.query_data(options.build().unwrap()).unwrap();
+            assert_eq!(ids(&result.data), ["cache-3"]);
+        }
+
+        #[test]
+        fn sort() {
+            let service = service();
+            let options = QueryOptions::default().sort(SortOrder::IdDescending);
+            let result = service.query_data(options.build().unwrap()).unwrap();
+            assert_eq!(ids(&result.data), ["cache-3", "cache-2", "cache-1"]);
+
+            let options = QueryOptions::default()
+                .sort(SortOrder::ValueAscending)
+                .limit(2);
+            let result = service.query_data(options.build().unwrap()).unwrap();
+            assert_eq!(ids(&result.data), ["cache-2", "cache-1"]);
+        }
+
+        #[test]
+        fn invalid_options() {
+            assert_eq!(
+                QueryOptions::default().limit(0).build().unwrap_err(),
+                OptionsError::ZeroLimit
+            );
+            assert_eq!(
+                QueryOptions::default()
+                    .timeout(Duration::ZERO)
+                    .build()
+                    .unwrap_err(),
+                OptionsError::ZeroTimeout
+            );
+            assert!(QueryOptions::default().limit(1).build().is_ok());
+        }
+
+        #[test]
+        fn metrics() {
+            let service = service();
+            service
+                .query_data(
+                    options(DataSource::Cache, EmptyDataOptions::Ignore)
+                        .build()
+                        .unwrap(),
+                )
+                .unwrap();
+            service.cache.invalidate(DATA_KEY);
+            service
+                .query_data(QueryOptions::default().build().unwrap())
+                .unwrap();
+            service
+                .query_data(QueryOptions::default().build().unwrap())
+                .unwrap();
+
+            let metrics = service.metrics().snapshot();
+            assert_eq!((metrics.cache_hits, metrics.cache_misses), (2, 1));
+            assert_eq!(metrics.filtered_items, 1);
+            assert_eq!(metrics.source(DataSource::Cache).latency.count, 1);
+            assert_eq!(metrics.source(DataSource::CacheOrApi).latency.count, 2);
+            assert_eq!(metrics.source(DataSource::CacheOrApi).errors, 0);
+
+            let rest_api = InMemoryRestService::new().respond(DATA_KEY, Err(unavailable()));
+            let service = DataService::new(InMemoryCache::new(), rest_api);
+            assert!(service
+                .query_data(QueryOptions::default().build().unwrap())
+                .is_err());
+
+            let metrics = service.metrics().snapshot();
+            assert_eq!(metrics.cache_misses, 1);
+            assert_eq!(metrics.source(DataSource::CacheOrApi).errors, 1);
+            assert_eq!(metrics.source(DataSource::CacheOrApi).latency.count, 1);
+        }
+
+        fn paged_service(pages: usize) -> DataService<InMemoryCache, InMemoryRestService> {
+            let rest_api = (FIRST_PAGE..FIRST_PAGE + pages).fold(
+                InMemoryRestService::new(),
+                |rest_api, page| {
+                    let items = items(&format!("page{page}"));
+                    rest_api.respond(&page_key(DATA_KEY, page), Ok(items))
+                },
+            );
+            DataService::new(InMemoryCache::new(), rest_api)
+        }
+
+        fn stream_ids<C: DataCache, R: RestService>(
+            service: &DataService<C, R>,
+            options: QueryOptions,
+        ) -> Result<Vec<String>, QueryError> {
+            service
+                .query_stream(options.build().unwrap())
+                .map(|item| item.map(|item| item.id))
+                .collect()
+        }
+
+        fn fetched_pages<C, R>(service: &DataService<C, R>, source: DataSource) -> u64 {
+            service.metrics.snapshot().source(source).latency.count
+        }
+
+        #[test]
+        fn stream_pages() {
+            let service = paged_service(3);
+            let ids = stream_ids(
+                &service,
+                options(DataSource::ApiService, EmptyDataOptions::Ignore),
+            );
+            assert_eq!(
+                ids.unwrap(),
+                ["page1-1", "page1-3", "page2-1", "page2-3", "page3-1", "page3-3"]
+            );
+            assert_eq!(service.metrics().snapshot().filtered_items, 3);
+
+            // Pages aren't stored in the cache while streaming.
+            let ids = stream_ids(&service, options(DataSource::Cache, EmptyDataOptions::Keep));
+            assert!(matches!(ids, Err(QueryError::NotFound(_))));
+        }
+
+        #[test]
+        fn stream_lazily() {
+            let service = paged_service(3);
+            let limited = options(DataSource::ApiService, EmptyDataOptions::Keep).limit(2);
+            assert_eq!(
+                stream_ids(&service, limited).unwrap(),
+                ["page1-1", "page1-2"]
+            );
+            assert_eq!(fetched_pages(&service, DataSource::ApiService), 1);
+
+            let service = paged_service(3);
+            let paged = options(DataSource::ApiService, EmptyDataOptions::Ignore)
+                .offset(4)
+                .limit(1);
+            assert_eq!(stream_ids(&service, paged).unwrap(), ["page3-1"]);
+            assert_eq!(fetched_pages(&service, DataSource::ApiService), 3);
+        }
+
+        #[test]
+        fn stream_errors() {
+            let service = paged_service(0);
+            let api = options(DataSource::ApiService, EmptyDataOptions::Keep);
+            let mut stream = service.query_stream(api.build().unwrap());
+            assert!(matches!(stream.next(), Some(Err(QueryError::NotFound(_)))));
+            assert!(stream.next().is_none());
+
+            let rest_api = InMemoryRestService::new()
+                .respond(&page_key(DATA_KEY, 1), Ok(items("page1")))
+                .respond(&page_key(DATA_KEY, 2), Err(unavailable()));
+            let service = DataService::new(InMemoryCache::new(), rest_api);
+            let api = options(DataSource::ApiService, EmptyDataOptions::Keep);
+            let items: Vec<_> = service.query_stream(api.build().unwrap()).collect();
+            assert_eq!(items.len(), 4);
+            assert_eq!(items[3], Err(unavailable()));
+
+            let sorted = options(DataSource::ApiService, EmptyDataOptions::Keep)
+                .sort(SortOrder::IdAscending);
+            assert_eq!(
+                stream_ids(&service, sorted),
+                Err(QueryError::InvalidOptions(OptionsError::SortNotStreamable))
+            );
+            let deduped = options(
+                DataSource::ApiService,
+                EmptyDataOptions::Ignore.then(EmptyDataOptions::Dedupe),
+            );
+            assert_eq!(
+                stream_ids(&service, deduped),
+                Err(QueryError::InvalidOptions(
+                    OptionsError::DedupeNotStreamable
+                ))
+            );
+            for source in [
+                DataSource::CacheOrApi,
+                DataSource::ApiThenCache,
+                DataSource::Refresh,
+            ] {
+                assert_eq!(
+                    stream_ids(&service, options(source, EmptyDataOptions::Keep)),
+                    Err(QueryError::InvalidOptions(
+                        OptionsError::SourceNotStreamable(source)
+                    ))
+                );
+            }
+            assert_eq!(fetched_pages(&service, DataSource::Refresh), 0);
+        }
+
+        /// API generating its pages on request.
+        struct GeneratedPages {
+            pages: usize,
+            page_size: usize,
+        }
+
+        impl DataSourceBackend for GeneratedPages {
+            fn query(&self, key: &str) -> Result<Vec<Data>, QueryError> {
+                let page: usize = key
+                    .strip_prefix("/data?page=")
+                    .and_then(|page| page.parse().ok())
+                    .ok_or_else(|| QueryError::NotFound(key.to_owned()))?;
+                if page > self.pages {
+                    return Ok(Vec::new());
+                }
+                Ok((0..self.page_size)
+                    .map(|idx| Data::new(format!("{page}-{idx}"), "value"))
+                    .collect())
+            }
+        }
+
+        impl RestService for GeneratedPages {}
+
+        #[test]
+        fn stream_large_result() {
+            let rest_api = GeneratedPages {
+                pages: 1000,
+                page_size: 100,
+            };
+            let service = DataService::new(InMemoryCache::new(), rest_api);
+            let options = options(DataSource::ApiService, EmptyDataOptions::Ignore);
+
+            let count = service
+                .query_stream(options.build().unwrap())
+                .map(Result::unwrap)
+                .count();
+            assert_eq!(count, 100_000);
+            assert_eq!(fetched_pages(&service, DataSource::ApiService), 1001);
+        }
+    }
 }
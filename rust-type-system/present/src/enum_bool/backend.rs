@@ -0,0 +1,133 @@
+//! Backends the [`DataService`](super::good::DataService) can query data from.
+
+use std::{collections::HashMap, fmt, time::Duration};
+
+use super::good::OptionsError;
+
+/// Item returned from the data sources.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Data {
+    pub id: String,
+    pub value: String,
+}
+
+impl Data {
+    pub fn new(id: impl Into<String>, value: impl Into<String>) -> Self {
+        Self {
+            id: id.into(),
+            value: value.into(),
+        }
+    }
+
+    /// Data without value is considered empty.
+    pub fn is_empty(&self) -> bool {
+        self.value.is_empty()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum QueryError {
+    /// No data is stored under the queried key.
+    NotFound(String),
+    /// Backend failed to answer the query.
+    Backend(String),
+    /// Server answered with an error status.
+    Http(u16),
+    Timeout,
+    Io(String),
+    /// Server answer couldn't be understood.
+    InvalidResponse(String),
+    /// Query options were rejected before querying any backend.
+    InvalidOptions(OptionsError),
+    /// Requests are rejected as the backend failed too often recently.
+    CircuitOpen,
+}
+
+impl fmt::Display for QueryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            QueryError::NotFound(key) => write!(f, "No data found for '{key}'"),
+            QueryError::Backend(msg) => write!(f, "Backend error: {msg}"),
+            QueryError::Http(status) => write!(f, "Server answered with status {status}"),
+            QueryError::Timeout => f.write_str("Request timed out"),
+            QueryError::Io(msg) => write!(f, "IO error: {msg}"),
+            QueryError::InvalidResponse(msg) => write!(f, "Invalid response: {msg}"),
+            QueryError::InvalidOptions(err) => write!(f, "Invalid query options: {err}"),
+            QueryError::CircuitOpen => f.write_str("Circuit breaker is open"),
+        }
+    }
+}
+
+impl QueryError {
+    /// Checks if the error may not happen again when repeating the query.
+    pub fn is_transient(&self) -> bool {
+        match self {
+            QueryError::Timeout | QueryError::Io(_) | QueryError::Backend(_) => true,
+            // Server errors and rate limiting.
+            QueryError::Http(status) => *status >= 500 || *status == 429,
+            QueryError::NotFound(_)
+            | QueryError::InvalidResponse(_)
+            | QueryError::InvalidOptions(_)
+            | QueryError::CircuitOpen => false,
+        }
+    }
+}
+
+impl std::error::Error for QueryError {}
+
+/// Source which data can be queried from.
+pub trait DataSourceBackend {
+    /// Queries the data stored under the given key.
+    fn query(&self, key: &str) -> Result<Vec<Data>, QueryError>;
+
+    /// Queries the data, giving up after the timeout if the backend
+    /// supports it.
+    fn query_with_timeout(&self, key: &str, timeout: Duration) -> Result<Vec<Data>, QueryError> {
+        let _ = timeout;
+        self.query(key)
+    }
+}
+
+/// Local cache of data.
+pub trait DataCache: DataSourceBackend {
+    /// Stores the data under the given key, replacing the existing data.
+    fn store(&self, key: &str, data: Vec<Data>);
+
+    /// Removes the data stored under the given key.
+    fn invalidate(&self, key: &str);
+
+    /// Removes the data of all keys starting with the given prefix.
+    fn invalidate_prefix(&self, prefix: &str);
+}
+
+/// Remote REST API where the key is the path of the request.
+pub trait RestService: DataSourceBackend {}
+
+/// REST service answering from fixed responses in memory.
+#[derive(Debug, Default)]
+pub struct InMemoryRestService {
+    responses: HashMap<String, Result<Vec<Data>, QueryError>>,
+}
+
+impl InMemoryRestService {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the response for requests to the given path.
+    pub fn respond(mut self, path: &str, response: Result<Vec<Data>, QueryError>) -> Self {
+        self.responses.insert(path.to_owned(), response);
+        self
+    }
+}
+
+impl DataSourceBackend for InMemoryRestService {
+    fn query(&self, key: &str) -> Result<Vec<Data>, QueryError> {
+        self.responses
+            .get(key)
+            .cloned()
+            .unwrap_or_else(|| Err(QueryError::NotFound(key.to_owned())))
+    }
+}
+
+impl RestService for InMemoryRestService {}
@@ -0,0 +1,279 @@
+//! In memory [`DataCache`] with expiry and capacity limits.
+
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use super::{
+    backend::{Data, DataCache, DataSourceBackend, QueryError},
+    clock::{Clock, SystemClock},
+};
+
+/// Limits of the cache on how long and how many entries are kept.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CachePolicy {
+    /// Time to live of each entry after storing it. Entries never
+    /// expire when not set or when the expiry is beyond the range of `Instant`.
+    pub ttl: Option<Duration>,
+    /// Maximum count of entries. Least recently used entries are evicted
+    /// once exceeded. Capacity is unlimited when not set.
+    pub capacity: Option<usize>,
+}
+
+impl CachePolicy {
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = Some(ttl);
+        self
+    }
+
+    pub fn with_capacity(mut self, capacity: usize) -> Self {
+        self.capacity = Some(capacity);
+        self
+    }
+}
+
+#[derive(Debug)]
+struct Entry {
+    data: Vec<Data>,
+    expires_at: Option<Instant>,
+    /// Value of the usage counter on the last access.
+    last_used: u64,
+}
+
+#[derive(Debug, Default)]
+struct Entries {
+    map: HashMap<String, Entry>,
+    /// Counter increased on each access to order entries by usage.
+    usage: u64,
+}
+
+impl Entries {
+    fn next_usage(&mut self) -> u64 {
+        self.usage += 1;
+        self.usage
+    }
+
+    fn remove_expired(&mut self, now: Instant) {
+        self.map
+            .retain(|_, entry| entry.expires_at.is_none_or(|expires| expires > now));
+    }
+
+    fn evict_least_used(&mut self, capacity: usize) {
+        while self.map.len() > capacity {
+            let Some(key) = self
+                .map
+                .iter()
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(key, _)| key.clone())
+            else {
+                return;
+            };
+            self.map.remove(&key);
+        }
+    }
+}
+
+/// Cache keeping all data in memory.
+#[derive(Debug)]
+pub struct InMemoryCache<C = SystemClock> {
+    entries: Mutex<Entries>,
+    policy: CachePolicy,
+    clock: C,
+}
+
+impl InMemoryCache {
+    /// Creates a cache without expiry and capacity limits.
+    pub fn new() -> Self {
+        Self::with_policy(CachePolicy::default())
+    }
+
+    pub fn with_policy(policy: CachePolicy) -> Self {
+        Self::with_clock(policy, SystemClock)
+    }
+}
+
+impl Default for InMemoryCache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<C: Clock> InMemoryCache<C> {
+    /// Creates a cache using the given clock to expire entries.
+    pub fn with_clock(policy: CachePolicy, clock: C) -> Self {
+        Self {
+            entries: Mutex::new(Entries::default()),
+            policy,
+            clock,
+        }
+    }
+
+    /// Stores the data with a time to live different from the policy.
+    pub fn store_with_ttl(&self, key: &str, data: Vec<Data>, ttl: Option<Duration>) {
+        let now = self.clock.now();
+        let mut entries = self.entries.lock().unwrap();
+        let last_used = entries.next_usage();
+        entries.map.insert(
+            key.to_owned(),
+            Entry {
+                data,
+                // Expiry too far in the future to represent means never.
+                expires_at: ttl.and_then(|ttl| now.checked_add(ttl)),
+                last_used,
+            },
+        );
+
+        if let Some(capacity) = self.policy.capacity {
+            // Make room by dropping expired entries before evicting used ones.
+            entries.remove_expired(now);
+            entries.evict_least_used(capacity);
+        }
+    }
+
+    /// Count of stored entries, including expired ones which aren't removed yet.
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap().map.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<C: Clock> DataSourceBackend for InMemoryCache<C> {
+    fn query(&self, key: &str) -> Result<Vec<Data>, QueryError> {
+        let now = self.clock.now();
+        let mut entries = self.entries.lock().unwrap();
+        let last_used = entries.next_usage();
+
+        let Some(entry) = entries.map.get_mut(key) else {
+            return Err(QueryError::NotFound(key.to_owned()));
+        };
+
+        if entry.expires_at.is_some_and(|expires| expires <= now) {
+            entries.map.remove(key);
+            return Err(QueryError::NotFound(key.to_owned()));
+        }
+
+        entry.last_used = last_used;
+        Ok(entry.data.clone())
+    }
+}
+
+impl<C: Clock> DataCache for InMemoryCache<C> {
+    fn store(&self, key: &str, data: Vec<Data>) {
+        self.store_with_ttl(key, data, self.policy.ttl);
+    }
+
+    fn invalidate(&self, key: &str) {
+        self.entries.lock().unwrap().map.remove(key);
+    }
+
+    fn invalidate_prefix(&self, prefix: &str) {
+        self.entries
+            .lock()
+            .unwrap()
+            .map
+            .retain(|key, _| !key.starts_with(prefix));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::enum_bool::clock::MockClock;
+
+    fn data(id: &str) -> Vec<Data> {
+        vec![Data::new(id, "value")]
+    }
+
+    fn is_cached<C: Clock>(cache: &InMemoryCache<C>, key: &str) -> bool {
+        cache.query(key).is_ok()
+    }
+
+    #[test]
+    fn ttl_expiry() {
+        let clock = MockClock::new();
+        let policy = CachePolicy::default().with_ttl(Duration::from_secs(10));
+        let cache = InMemoryCache::with_clock(policy, clock.clone());
+
+        cache.store("/a", data("a"));
+        cache.store_with_ttl("/b", data("b"), Some(Duration::from_secs(60)));
+        cache.store_with_ttl("/c", data("c"), None);
+
+        clock.advance(Duration::from_secs(9));
+        assert_eq!(cache.query("/a"), Ok(data("a")));
+
+        clock.advance(Duration::from_secs(1));
+        assert_eq!(cache.query("/a"), Err(QueryError::NotFound("/a".into())));
+        assert!(is_cached(&cache, "/b"));
+
+        clock.advance(Duration::from_secs(3600));
+        assert!(!is_cached(&cache, "/b"));
+        assert!(is_cached(&cache, "/c"));
+    }
+
+    #[test]
+    fn huge_ttl_never_expires() {
+        let clock = MockClock::new();
+        let policy = CachePolicy::default().with_ttl(Duration::MAX);
+        let cache = InMemoryCache::with_clock(policy, clock.clone());
+
+        cache.store("/a", data("a"));
+        clock.advance(Duration::from_secs(365 * 24 * 3600));
+        assert!(is_cached(&cache, "/a"));
+    }
+
+    #[test]
+    fn lru_eviction() {
+        let cache = InMemoryCache::with_policy(CachePolicy::default().with_capacity(2));
+
+        cache.store("/a", data("a"));
+        cache.store("/b", data("b"));
+        // Using `a` makes `b` the least recently used entry.
+        assert!(is_cached(&cache, "/a"));
+        cache.store("/c", data("c"));
+
+        assert_eq!(cache.len(), 2);
+        assert!(is_cached(&cache, "/a"));
+        assert!(!is_cached(&cache, "/b"));
+        assert!(is_cached(&cache, "/c"));
+    }
+
+    #[test]
+    fn expired_entries_evicted_first() {
+        let clock = MockClock::new();
+        let policy = CachePolicy::default().with_capacity(2);
+        let cache = InMemoryCache::with_clock(policy, clock.clone());
+
+        cache.store("/a", data("a"));
+        cache.store_with_ttl("/b", data("b"), Some(Duration::from_secs(1)));
+        clock.advance(Duration::from_secs(2));
+        cache.store("/c", data("c"));
+
+        assert!(is_cached(&cache, "/a"));
+        assert!(is_cached(&cache, "/c"));
+    }
+
+    #[test]
+    fn invalidation() {
+        let cache = InMemoryCache::new();
+        cache.store("/data", data("data"));
+        cache.store("/data?page=1", data("page-1"));
+        cache.store("/data?page=2", data("page-2"));
+        cache.store("/other", data("other"));
+
+        cache.invalidate("/other");
+        assert!(!is_cached(&cache, "/other"));
+        assert_eq!(cache.len(), 3);
+
+        cache.invalidate_prefix("/data?");
+        assert!(is_cached(&cache, "/data"));
+        assert_eq!(cache.len(), 1);
+
+        cache.invalidate_prefix("");
+        assert!(cache.is_empty());
+    }
+}
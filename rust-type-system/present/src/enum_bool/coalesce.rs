@@ -0,0 +1,174 @@
+//! Deduplication of identical calls running at the same time.
+
+use std::{
+    collections::HashMap,
+    hash::Hash,
+    sync::{Arc, Condvar, Mutex},
+};
+
+#[derive(Debug)]
+enum State<V> {
+    Running,
+    Done(V),
+    /// The call panicked, so one of the waiting callers runs it again.
+    Abandoned,
+}
+
+#[derive(Debug)]
+struct Flight<V> {
+    state: Mutex<State<V>>,
+    finished: Condvar,
+}
+
+/// Shares the result of a running call with all callers asking for the
+/// same key meanwhile, instead of running the call again for each of them.
+#[derive(Debug)]
+pub struct Coalescer<K, V> {
+    flights: Mutex<HashMap<K, Arc<Flight<V>>>>,
+}
+
+impl<K, V> Default for Coalescer<K, V> {
+    fn default() -> Self {
+        Self {
+            flights: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+impl<K: Eq + Hash + Clone, V: Clone> Coalescer<K, V> {
+    /// Runs the call, or waits for the call already running for the key
+    /// and returns its result. `joined` is called each time the caller
+    /// starts waiting for another call.
+    pub fn run(&self, key: K, joined: impl Fn(), call: impl FnOnce() -> V) -> V {
+        loop {
+            let (flight, leader) = {
+                let mut flights = self.flights.lock().unwrap();
+                match flights.get(&key) {
+                    Some(flight) => (Arc::clone(flight), false),
+                    None => {
+                        let flight = Arc::new(Flight {
+                            state: Mutex::new(State::Running),
+                            finished: Condvar::new(),
+                        });
+                        flights.insert(key.clone(), Arc::clone(&flight));
+                        (flight, true)
+                    }
+                }
+            };
+
+            if leader {
+                let guard = Leader {
+                    coalescer: self,
+                    key: &key,
+                    flight: &flight,
+                    value: None,
+                };
+                return guard.finish(call());
+            }
+
+            joined();
+            let mut state = flight.state.lock().unwrap();
+            while matches!(*state, State::Running) {
+                state = flight.finished.wait(state).unwrap();
+            }
+            if let State::Done(value) = &*state {
+                return value.clone();
+            }
+            // Retry with the next caller leading once the leader panicked.
+        }
+    }
+}
+
+/// Publishes the result of the call, or abandons the flight if the call
+/// panics before.
+struct Leader<'a, K: Eq + Hash, V> {
+    coalescer: &'a Coalescer<K, V>,
+    key: &'a K,
+    flight: &'a Flight<V>,
+    value: Option<V>,
+}
+
+impl<K: Eq + Hash, V: Clone> Leader<'_, K, V> {
+    fn finish(mut self, value: V) -> V {
+        self.value = Some(value.clone());
+        value
+    }
+}
+
+impl<K: Eq + Hash, V> Drop for Leader<'_, K, V> {
+    fn drop(&mut self) {
+        // New callers must not join the flight once its result is published.
+        self.coalescer.flights.lock().unwrap().remove(self.key);
+        let mut state = self
+            .flight
+            .state
+            .lock()
+            .unwrap_or_else(|err| err.into_inner());
+        *state = match self.value.take() {
+            Some(value) => State::Done(value),
+            None => State::Abandoned,
+        };
+        self.flight.finished.notify_all();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        panic::{self, AssertUnwindSafe},
+        sync::atomic::{AtomicUsize, Ordering},
+        thread,
+    };
+
+    use super::*;
+
+    #[test]
+    fn share_running_call() {
+        let coalescer = Coalescer::default();
+        let calls = AtomicUsize::new(0);
+        let joined = AtomicUsize::new(0);
+
+        let results: Vec<_> = thread::scope(|scope| {
+            let handles: Vec<_> = (0..8)
+                .map(|_| {
+                    scope.spawn(|| {
+                        let join = || {
+                            joined.fetch_add(1, Ordering::SeqCst);
+                        };
+                        coalescer.run("key", join, || {
+                            calls.fetch_add(1, Ordering::SeqCst);
+                            // Finish once all other callers wait for this call.
+                            while joined.load(Ordering::SeqCst) < 7 {
+                                thread::yield_now();
+                            }
+                            Ok::<_, String>(42)
+                        })
+                    })
+                })
+                .collect();
+            handles.into_iter().map(|h| h.join().unwrap()).collect()
+        });
+
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        assert!(results.iter().all(|result| *result == Ok(42)));
+    }
+
+    #[test]
+    fn separate_keys_and_calls() {
+        let coalescer = Coalescer::default();
+        assert_eq!(coalescer.run(1, || {}, || "first"), "first");
+        // Finished calls aren't cached.
+        assert_eq!(coalescer.run(1, || {}, || "second"), "second");
+        assert_eq!(coalescer.run(2, || {}, || "other"), "other");
+    }
+
+    #[test]
+    fn panicking_call() {
+        let coalescer = Coalescer::default();
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            coalescer.run("key", || {}, || -> u32 { panic!("call failed") })
+        }));
+        assert!(result.is_err());
+        assert_eq!(coalescer.run("key", || {}, || 1), 1);
+    }
+}
@@ -0,0 +1,583 @@
+//! [`DataCache`] persisting its entries to a directory, so the cache is
+//! still warm after a restart.
+//!
+//! Each entry is stored in its own JSON file:
+//!
+//! ```json
+//! {"version":2,"key":"/data","data":[{"id":"sensor-1","value":"21.5"}]}
+//! ```
+//!
+//! Version 1 files don't contain the key, which is taken from the file name
+//! instead. They are rewritten in the current version when opening the cache.
+//!
+//! File names are the hex encoded key, or a hash of keys too long for a file
+//! name. Long keys with the same hash share a file, so only the last stored
+//! of them survives a restart. Files not named like that are left alone, so
+//! the directory can be shared with other files.
+
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use super::{
+    backend::{Data, DataCache, DataSourceBackend, QueryError},
+    json::Json,
+};
+
+/// Version of the files written by this cache.
+pub const FORMAT_VERSION: u64 = 2;
+
+const EXTENSION: &str = "json";
+const TEMP_EXTENSION: &str = "tmp";
+const FILE_PREFIX: &str = "key-";
+const HASH_PREFIX: &str = "hash-";
+/// Longest file name supported by common file systems.
+const MAX_FILE_NAME: usize = 255;
+
+/// Changes made to the directory while opening the cache.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Recovery {
+    /// Keys of the entries rewritten from an older version.
+    pub migrated: Vec<String>,
+    /// Corrupted files and temporary files of interrupted writes, which
+    /// were removed.
+    pub discarded: Vec<PathBuf>,
+    /// Files of a newer version or which couldn't be read. They are kept
+    /// but not loaded, and never overwritten or removed.
+    pub skipped: Vec<PathBuf>,
+}
+
+#[derive(Debug)]
+enum LoadError {
+    Io(io::Error),
+    Corrupted(String),
+    UnsupportedVersion(u64),
+}
+
+impl From<io::Error> for LoadError {
+    fn from(err: io::Error) -> Self {
+        LoadError::Io(err)
+    }
+}
+
+/// Cache keeping the entries in memory and writing each change through to
+/// its file in the directory.
+///
+/// Files are replaced atomically by renaming a completely written temporary
+/// file, so a crash leaves either the old or the new entry behind.
+#[derive(Debug)]
+pub struct FileCache {
+    dir: PathBuf,
+    entries: Mutex<HashMap<String, Vec<Data>>>,
+    recovery: Recovery,
+}
+
+impl FileCache {
+    /// Opens the cache in the directory, creating it if needed and loading
+    /// the stored entries.
+    pub fn open(dir: impl Into<PathBuf>) -> io::Result<Self> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)?;
+
+        let mut entries = HashMap::new();
+        let mut recovery = Recovery::default();
+        for dir_entry in fs::read_dir(&dir)? {
+            let path = dir_entry?.path();
+            let stem = path.file_stem().and_then(|stem| stem.to_str());
+            if !stem.is_some_and(is_cache_stem) {
+                continue;
+            }
+            match path.extension().and_then(|ext| ext.to_str()) {
+                Some(EXTENSION) => {}
+                Some(TEMP_EXTENSION) => {
+                    discard(path, &mut recovery);
+                    continue;
+                }
+                _ => continue,
+            }
+
+            match load(&path) {
+                Ok((key, data, version)) => {
+                    if version < FORMAT_VERSION {
+                        match write_entry(&dir, &key, &data) {
+                            Ok(()) => recovery.migrated.push(key.clone()),
+                            // It's migrated again when opening the cache next time.
+                            Err(err) => log::warn!("Failed to migrate cache entry '{key}': {err}"),
+                        }
+                    }
+                    entries.insert(key, data);
+                }
+                Err(LoadError::Io(err)) => {
+                    log::warn!("Skipping unreadable cache file {}: {err}", path.display());
+                    recovery.skipped.push(path);
+                }
+                Err(LoadError::Corrupted(reason)) => {
+                    log::warn!(
+                        "Discarding corrupted cache file {}: {reason}",
+                        path.display()
+                    );
+                    discard(path, &mut recovery);
+                }
+                Err(LoadError::UnsupportedVersion(version)) => {
+                    log::warn!(
+                        "Skipping cache file {} of unsupported version {version}",
+                        path.display()
+                    );
+                    recovery.skipped.push(path);
+                }
+            }
+        }
+
+        Ok(Self {
+            dir,
+            entries: Mutex::new(entries),
+            recovery,
+        })
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Changes made to the directory while opening the cache.
+    pub fn recovery(&self) -> &Recovery {
+        &self.recovery
+    }
+
+    /// Path of the entry file, unless it's a skipped file which must be left
+    /// untouched.
+    fn writable_path(&self, key: &str) -> Option<PathBuf> {
+        let path = entry_path(&self.dir, key);
+        if self.recovery.skipped.contains(&path) {
+            log::warn!("Not persisting cache entry '{key}' over skipped file");
+            return None;
+        }
+        Some(path)
+    }
+
+    fn remove_file(&self, key: &str) {
+        let Some(path) = self.writable_path(key) else {
+            return;
+        };
+        match fs::remove_file(path) {
+            Ok(()) => {}
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+            Err(err) => log::warn!("Failed to remove cache file of '{key}': {err}"),
+        }
+    }
+}
+
+impl DataSourceBackend for FileCache {
+    fn query(&self, key: &str) -> Result<Vec<Data>, QueryError> {
+        self.entries
+            .lock()
+            .unwrap()
+            .get(key)
+            .cloned()
+            .ok_or_else(|| QueryError::NotFound(key.to_owned()))
+    }
+}
+
+impl DataCache for FileCache {
+    fn store(&self, key: &str, data: Vec<Data>) {
+        let mut entries = self.entries.lock().unwrap();
+        // The entry stays in memory even if it can't be persisted.
+        if let Some(path) = self.writable_path(key) {
+            if let Err(err) = write_atomically(&path, &encode_entry(key, &data)) {
+                log::warn!("Failed to persist cache entry '{key}': {err}");
+            }
+        }
+        entries.insert(key.to_owned(), data);
+    }
+
+    fn invalidate(&self, key: &str) {
+        let mut entries = self.entries.lock().unwrap();
+        entries.remove(key);
+        self.remove_file(key);
+    }
+
+    fn invalidate_prefix(&self, prefix: &str) {
+        let mut entries = self.entries.lock().unwrap();
+        entries.retain(|key, _| {
+            let keep = !key.starts_with(prefix);
+            if !keep {
+                self.remove_file(key);
+            }
+            keep
+        });
+    }
+}
+
+/// Removes the file, keeping it if that fails since it's skipped anyway.
+fn discard(path: PathBuf, recovery: &mut Recovery) {
+    match fs::remove_file(&path) {
+        Ok(()) => recovery.discarded.push(path),
+        Err(err) => {
+            log::warn!("Failed to remove cache file {}: {err}", path.display());
+            recovery.skipped.push(path);
+        }
+    }
+}
+
+/// Checks if the file stem is named like the files of this cache.
+fn is_cache_stem(stem: &str) -> bool {
+    stem.starts_with(FILE_PREFIX) || stem.starts_with(HASH_PREFIX)
+}
+
+/// File name of the key, hex encoded so any key is a valid file name.
+/// Keys too long for that are hashed instead.
+fn file_stem(key: &str) -> String {
+    let max_length = MAX_FILE_NAME - FILE_PREFIX.len() - ".".len() - EXTENSION.len();
+    if key.len() * 2 > max_length {
+        return format!("{HASH_PREFIX}{:016x}", fnv1a(key.as_bytes()));
+    }
+
+    let hex: String = key.bytes().map(|byte| format!("{byte:02x}")).collect();
+    format!("{FILE_PREFIX}{hex}")
+}
+
+/// 64 bit FNV-1a hash, which unlike the std hashers is stable across
+/// releases as needed for file names.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+fn key_from_stem(stem: &str) -> Option<String> {
+    let stem = stem.strip_prefix(FILE_PREFIX)?;
+    let bytes = (0..stem.len())
+        .step_by(2)
+        .map(|idx| u8::from_str_radix(stem.get(idx..idx + 2)?, 16).ok())
+        .collect::<Option<Vec<_>>>()?;
+    String::from_utf8(bytes).ok()
+}
+
+fn entry_path(dir: &Path, key: &str) -> PathBuf {
+    dir.join(format!("{}.{EXTENSION}", file_stem(key)))
+}
+
+fn encode_entry(key: &str, data: &[Data]) -> String {
+    Json::Object(vec![
+        ("version".into(), Json::Number(FORMAT_VERSION as f64)),
+        ("key".into(), Json::String(key.to_owned())),
+        (
+            "data".into(),
+            Json::Array(data.iter().map(Data::to_json).collect()),
+        ),
+    ])
+    .to_string()
+}
+
+fn write_entry(dir: &Path, key: &str, data: &[Data]) -> io::Result<()> {
+    write_atomically(&entry_path(dir, key), &encode_entry(key, data))
+}
+
+/// Writes the contents to a temporary file next to the path and renames it
+/// to replace the file at the path, so readers never see partial contents.
+pub(super) fn write_atomically(path: &Path, contents: &str) -> io::Result<()> {
+    let temp_path = path.with_extension(TEMP_EXTENSION);
+
+    let mut file = File::create(&temp_path)?;
+    file.write_all(contents.as_bytes())?;
+    // The content must be on disk before the rename makes it visible.
+    file.sync_all()?;
+    drop(file);
+
+    fs::rename(&temp_path, path)?;
+    // The rename is only durable once the directory is on disk too.
+    match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => sync_dir(dir),
+        _ => sync_dir(Path::new(".")),
+    }
+}
+
+#[cfg(unix)]
+fn sync_dir(dir: &Path) -> io::Result<()> {
+    File::open(dir)?.sync_all()
+}
+
+/// Directories can't be opened as files on other platforms.
+#[cfg(not(unix))]
+fn sync_dir(_dir: &Path) -> io::Result<()> {
+    Ok(())
+}
+
+/// Loads key, data and format version of the entry file.
+fn load(path: &Path) -> Result<(String, Vec<Data>, u64), LoadError> {
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(err) if err.kind() == io::ErrorKind::InvalidData => {
+            return Err(LoadError::Corrupted(err.to_string()))
+        }
+        Err(err) => return Err(err.into()),
+    };
+    let json = Json::parse(&text).map_err(|err| LoadError::Corrupted(err.to_string()))?;
+    let corrupted = |reason: &str| LoadError::Corrupted(reason.to_owned());
+
+    let version = json
+        .get("version")
+        .and_then(Json::as_u64)
+        .ok_or_else(|| corrupted("Missing version"))?;
+    let key = match version {
+        1 => path
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .and_then(key_from_stem)
+            .ok_or_else(|| corrupted("File name isn't an encoded key"))?,
+        FORMAT_VERSION => json
+            .get("key")
+            .and_then(Json::as_str)
+            .ok_or_else(|| corrupted("Missing key"))?
+            .to_owned(),
+        version => return Err(LoadError::UnsupportedVersion(version)),
+    };
+    let data = json
+        .get("data")
+        .and_then(Json::as_array)
+        .ok_or_else(|| corrupted("Missing data"))?
+        .iter()
+        .map(Data::from_json)
+        .collect::<Result<_, _>>()
+        .map_err(|err| LoadError::Corrupted(err.to_string()))?;
+
+    Ok((key, data, version))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use super::*;
+
+    /// Directory removed when dropped.
+    struct TestDir(PathBuf);
+
+    impl TestDir {
+        fn new(name: &str) -> Self {
+            let path =
+                env::temp_dir().join(format!("present-file-cache-{}-{name}", std::process::id()));
+            let _ = fs::remove_dir_all(&path);
+            Self(path)
+        }
+    }
+
+    impl Drop for TestDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn data(id: &str) -> Vec<Data> {
+        vec![Data::new(id, "value"), Data::new(format!("{id}-empty"), "")]
+    }
+
+    fn file_names(dir: &Path) -> Vec<String> {
+        let mut names: Vec<_> = fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn persist_entries() {
+        let dir = TestDir::new("persist");
+        let cache = FileCache::open(&dir.0).unwrap();
+        cache.store("/data", data("a"));
+        cache.store("/data?page=2", data("b"));
+        cache.store("/other", data("c"));
+        cache.store("/data", data("d"));
+        cache.invalidate("/other");
+        drop(cache);
+
+        let cache = FileCache::open(&dir.0).unwrap();
+        assert_eq!(cache.recovery(), &Recovery::default());
+        assert_eq!(cache.query("/data"), Ok(data("d")));
+        assert_eq!(cache.query("/data?page=2"), Ok(data("b")));
+        assert_eq!(
+            cache.query("/other"),
+            Err(QueryError::NotFound("/other".into()))
+        );
+
+        cache.invalidate_prefix("/data");
+        assert!(file_names(&dir.0).is_empty());
+    }
+
+    #[test]
+    fn atomic_writes() {
+        let dir = TestDir::new("atomic");
+        let cache = FileCache::open(&dir.0).unwrap();
+        cache.store("/data", data("a"));
+
+        // Only the renamed file is left behind.
+        assert_eq!(file_names(&dir.0), [format!("{}.json", file_stem("/data"))]);
+        let text = fs::read_to_string(entry_path(&dir.0, "/data")).unwrap();
+        assert_eq!(text, encode_entry("/data", &data("a")));
+    }
+
+    #[test]
+    fn crash_during_write() {
+        let dir = TestDir::new("crash");
+        let cache = FileCache::open(&dir.0).unwrap();
+        cache.store("/data", data("old"));
+        drop(cache);
+
+        // Crash after writing half of the new entry to the temporary file.
+        let temp_path = entry_path(&dir.0, "/data").with_extension(TEMP_EXTENSION);
+        let new_entry = encode_entry("/data", &data("new"));
+        fs::write(&temp_path, &new_entry[..new_entry.len() / 2]).unwrap();
+
+        let cache = FileCache::open(&dir.0).unwrap();
+        assert_eq!(cache.query("/data"), Ok(data("old")));
+        assert_eq!(cache.recovery().discarded, [temp_path]);
+        assert_eq!(file_names(&dir.0).len(), 1);
+    }
+
+    #[test]
+    fn corrupted_files() {
+        let dir = TestDir::new("corrupted");
+        let cache = FileCache::open(&dir.0).unwrap();
+        cache.store("/data", data("a"));
+        cache.store("/other", data("b"));
+        drop(cache);
+
+        let truncated = entry_path(&dir.0, "/other");
+        let text = fs::read_to_string(&truncated).unwrap();
+        fs::write(&truncated, &text[..text.len() - 3]).unwrap();
+        let garbage = dir.0.join(format!("{HASH_PREFIX}00ff.json"));
+        fs::write(&garbage, [0xff, 0xfe, 0x00]).unwrap();
+        let no_data = entry_path(&dir.0, "/no-data");
+        fs::write(&no_data, r#"{"version":2,"key":"/no-data"}"#).unwrap();
+
+        let cache = FileCache::open(&dir.0).unwrap();
+        assert_eq!(cache.query("/data"), Ok(data("a")));
+        assert!(cache.query("/other").is_err());
+        let mut discarded = cache.recovery().discarded.clone();
+        discarded.sort();
+        let mut expected = vec![truncated, garbage, no_data];
+        expected.sort();
+        assert_eq!(discarded, expected);
+        assert_eq!(file_names(&dir.0).len(), 1);
+    }
+
+    #[test]
+    fn keep_foreign_files() {
+        let dir = TestDir::new("foreign");
+        fs::create_dir_all(&dir.0).unwrap();
+        let foreign = ["package.json", "notes.tmp", "README", "keys.json"];
+        for name in foreign {
+            fs::write(dir.0.join(name), "not a cache entry").unwrap();
+        }
+
+        let cache = FileCache::open(&dir.0).unwrap();
+        cache.store("/data", data("a"));
+        assert_eq!(cache.recovery(), &Recovery::default());
+        drop(cache);
+
+        let cache = FileCache::open(&dir.0).unwrap();
+        assert_eq!(cache.query("/data"), Ok(data("a")));
+        assert_eq!(cache.recovery(), &Recovery::default());
+        assert_eq!(file_names(&dir.0).len(), foreign.len() + 1);
+        for name in foreign {
+            assert_eq!(
+                fs::read_to_string(dir.0.join(name)).unwrap(),
+                "not a cache entry"
+            );
+        }
+    }
+
+    #[test]
+    fn migrate_version_1() {
+        let dir = TestDir::new("migrate");
+        fs::create_dir_all(&dir.0).unwrap();
+        let path = entry_path(&dir.0, "/data");
+        fs::write(
+            &path,
+            r#"{"version":1,"data":[{"id":"a","value":"value"},{"id":"a-empty","value":""}]}"#,
+        )
+        .unwrap();
+
+        let cache = FileCache::open(&dir.0).unwrap();
+        assert_eq!(cache.query("/data"), Ok(data("a")));
+        assert_eq!(cache.recovery().migrated, ["/data"]);
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            encode_entry("/data", &data("a"))
+        );
+
+        let cache = FileCache::open(&dir.0).unwrap();
+        assert_eq!(cache.recovery(), &Recovery::default());
+    }
+
+    #[test]
+    fn skip_newer_versions() {
+        let dir = TestDir::new("newer");
+        fs::create_dir_all(&dir.0).unwrap();
+        let path = entry_path(&dir.0, "/data");
+        fs::write(&path, r#"{"version":3,"key":"/data","entries":[]}"#).unwrap();
+
+        let cache = FileCache::open(&dir.0).unwrap();
+        assert!(cache.query("/data").is_err());
+        assert_eq!(cache.recovery().skipped, std::slice::from_ref(&path));
+        assert!(path.exists());
+
+        // The newer file is kept, while the entry is still cached in memory.
+        let newer = fs::read_to_string(&path).unwrap();
+        cache.store("/data", data("a"));
+        assert_eq!(cache.query("/data"), Ok(data("a")));
+        assert_eq!(fs::read_to_string(&path).unwrap(), newer);
+        cache.invalidate("/data");
+        assert!(path.exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn skip_unreadable_files() {
+        let dir = TestDir::new("unreadable");
+        let cache = FileCache::open(&dir.0).unwrap();
+        cache.store("/data", data("a"));
+        drop(cache);
+
+        // Reading a directory fails with an I/O error other than bad data.
+        let unreadable = entry_path(&dir.0, "/dir");
+        fs::create_dir(&unreadable).unwrap();
+
+        let cache = FileCache::open(&dir.0).unwrap();
+        assert_eq!(cache.query("/data"), Ok(data("a")));
+        assert_eq!(cache.recovery().skipped, [unreadable]);
+    }
+
+    #[test]
+    fn long_keys() {
+        let dir = TestDir::new("long");
+        let cache = FileCache::open(&dir.0).unwrap();
+        let long = format!("/data?filter={}", "x".repeat(1000));
+        cache.store(&long, data("a"));
+        cache.store("/data", data("b"));
+        drop(cache);
+
+        let cache = FileCache::open(&dir.0).unwrap();
+        assert_eq!(cache.query(&long), Ok(data("a")));
+        assert_eq!(cache.query("/data"), Ok(data("b")));
+        assert!(file_names(&dir.0)
+            .iter()
+            .all(|name| name.len() <= MAX_FILE_NAME));
+        assert!(file_stem(&long).starts_with(HASH_PREFIX));
+    }
+
+    #[test]
+    fn key_encoding() {
+        for key in ["/data", "", "/päge?x=1&y=ü"] {
+            assert_eq!(key_from_stem(&file_stem(key)).as_deref(), Some(key));
+        }
+        assert_eq!(key_from_stem("key-2f6"), None);
+        assert_eq!(key_from_stem("key-zz"), None);
+        assert_eq!(key_from_stem("2f"), None);
+    }
+}
@@ -0,0 +1,516 @@
+//! Minimal HTTP/1.1 client implementing [`RestService`] and a local stand-in
+//! server to exercise it without network.
+
+use std::{
+    collections::HashMap,
+    io::{self, BufRead, BufReader, Read, Write},
+    net::{SocketAddr, TcpListener, TcpStream},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Condvar, Mutex,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+use super::{
+    backend::{Data, DataSourceBackend, QueryError, RestService},
+    json,
+};
+
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(5);
+/// Largest response body accepted, so a bogus content length can't exhaust
+/// the memory.
+const MAX_BODY_LENGTH: u64 = 16 * 1024 * 1024;
+/// Longest status, request or header line accepted, including the line break.
+const MAX_LINE_LENGTH: u64 = 8 * 1024;
+/// Most header lines accepted in one message.
+const MAX_HEADERS: usize = 100;
+/// Time after which a [`Gate`] opens by itself.
+const GATE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// REST service sending GET requests to the given address.
+#[derive(Debug, Clone)]
+pub struct HttpRestService {
+    addr: SocketAddr,
+    timeout: Duration,
+}
+
+impl HttpRestService {
+    pub fn new(addr: SocketAddr) -> Self {
+        Self {
+            addr,
+            timeout: DEFAULT_TIMEOUT,
+        }
+    }
+
+    /// Sets the time limit of each request from connecting until the whole
+    /// response is received.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    fn get(&self, path: &str, timeout: Duration) -> io::Result<Response> {
+        let deadline = Instant::now() + timeout;
+        let stream = TcpStream::connect_timeout(&self.addr, timeout)?;
+        let mut stream = DeadlineStream { stream, deadline };
+
+        write!(
+            stream,
+            "GET {path} HTTP/1.1\r\nHost: {}\r\nAccept: application/json\r\nConnection: close\r\n\r\n",
+            self.addr
+        )?;
+        stream.flush()?;
+
+        read_response(BufReader::new(stream))
+    }
+}
+
+impl DataSourceBackend for HttpRestService {
+    fn query(&self, key: &str) -> Result<Vec<Data>, QueryError> {
+        self.query_with_timeout(key, self.timeout)
+    }
+
+    fn query_with_timeout(&self, key: &str, timeout: Duration) -> Result<Vec<Data>, QueryError> {
+        let response = self.get(key, timeout).map_err(QueryError::from)?;
+        match response.status {
+            200 => json::decode_data(&response.body)
+                .map_err(|err| QueryError::InvalidResponse(err.to_string())),
+            404 => Err(QueryError::NotFound(key.to_owned())),
+            status => Err(QueryError::Http(status)),
+        }
+    }
+}
+
+impl RestService for HttpRestService {}
+
+impl From<io::Error> for QueryError {
+    fn from(err: io::Error) -> Self {
+        match err.kind() {
+            // Timeouts are reported as `WouldBlock` on some platforms.
+            io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock => QueryError::Timeout,
+            io::ErrorKind::InvalidData => QueryError::InvalidResponse(err.to_string()),
+            _ => QueryError::Io(err.to_string()),
+        }
+    }
+}
+
+/// Stream failing with a timeout once the deadline passed. Socket timeouts
+/// only limit each read, so a server trickling the response would otherwise
+/// never time out.
+struct DeadlineStream {
+    stream: TcpStream,
+    deadline: Instant,
+}
+
+impl DeadlineStream {
+    fn remaining(&self) -> io::Result<Duration> {
+        self.deadline
+            .checked_duration_since(Instant::now())
+            .filter(|remaining| !remaining.is_zero())
+            .ok_or_else(|| io::ErrorKind::TimedOut.into())
+    }
+}
+
+impl Read for DeadlineStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.stream.set_read_timeout(Some(self.remaining()?))?;
+        self.stream.read(buf)
+    }
+}
+
+impl Write for DeadlineStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.stream.set_write_timeout(Some(self.remaining()?))?;
+        self.stream.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.stream.flush()
+    }
+}
+
+#[derive(Debug)]
+struct Response {
+    status: u16,
+    body: String,
+}
+
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+fn read_response<R: BufRead>(mut reader: R) -> io::Result<Response> {
+    let status_line = read_line(&mut reader)?;
+    let status = status_line
+        .strip_prefix("HTTP/1.")
+        .and_then(|rest| rest.split_whitespace().nth(1))
+        .and_then(|status| status.parse().ok())
+        .ok_or_else(|| invalid_data("Invalid HTTP status line"))?;
+
+    let mut content_length = None;
+    for (name, value) in read_headers(&mut reader)? {
+        if name.eq_ignore_ascii_case("content-length") {
+            let length = value
+                .parse()
+                .map_err(|_| invalid_data("Invalid content length"))?;
+            content_length = Some(length);
+        } else if name.eq_ignore_ascii_case("transfer-encoding") {
+            return Err(invalid_data("Transfer encoding isn't supported"));
+        }
+    }
+
+    let too_large = || invalid_data("Body is too large");
+    let mut body = Vec::new();
+    match content_length {
+        Some(length) if length > MAX_BODY_LENGTH => return Err(too_large()),
+        Some(length) => {
+            reader.take(length).read_to_end(&mut body)?;
+            if (body.len() as u64) < length {
+                return Err(io::ErrorKind::UnexpectedEof.into());
+            }
+        }
+        // Connection is closed after the response.
+        None => {
+            reader.take(MAX_BODY_LENGTH + 1).read_to_end(&mut body)?;
+            if body.len() as u64 > MAX_BODY_LENGTH {
+                return Err(too_large());
+            }
+        }
+    }
+
+    let body = String::from_utf8(body).map_err(|_| invalid_data("Body isn't valid UTF-8"))?;
+    Ok(Response { status, body })
+}
+
+/// Reads a line, failing if it's longer than [`MAX_LINE_LENGTH`].
+fn read_line<R: BufRead>(reader: &mut R) -> io::Result<String> {
+    let mut line = String::new();
+    reader
+        .by_ref()
+        .take(MAX_LINE_LENGTH + 1)
+        .read_line(&mut line)?;
+    if line.len() as u64 > MAX_LINE_LENGTH {
+        return Err(invalid_data("Line is too long"));
+    }
+    Ok(line)
+}
+
+/// Reads the header lines until the empty line separating them from the body.
+fn read_headers<R: BufRead>(reader: &mut R) -> io::Result<Vec<(String, String)>> {
+    let mut headers = Vec::new();
+    loop {
+        let line = read_line(reader)?;
+        if line.is_empty() {
+            return Err(invalid_data("Connection closed within headers"));
+        }
+
+        let line = line.trim_end();
+        if line.is_empty() {
+            return Ok(headers);
+        }
+        if headers.len() == MAX_HEADERS {
+            return Err(invalid_data("Too many headers"));
+        }
+
+        let (name, value) = line
+            .split_once(':')
+            .ok_or_else(|| invalid_data("Invalid header"))?;
+        headers.push((name.trim().to_owned(), value.trim().to_owned()));
+    }
+}
+
+/// Response the stand-in server sends for a path.
+#[derive(Debug, Clone)]
+pub struct Fixture {
+    pub status: u16,
+    pub body: String,
+    /// Delay before answering, used to provoke timeouts.
+    pub delay: Duration,
+    /// Gate to wait for before answering.
+    pub gate: Option<Gate>,
+}
+
+impl Fixture {
+    pub fn json(body: impl Into<String>) -> Self {
+        Self {
+            status: 200,
+            body: body.into(),
+            delay: Duration::ZERO,
+            gate: None,
+        }
+    }
+
+    pub fn data(data: &[Data]) -> Self {
+        Self::json(json::encode_data(data))
+    }
+
+    pub fn status(status: u16) -> Self {
+        Self {
+            status,
+            body: String::new(),
+            delay: Duration::ZERO,
+            gate: None,
+        }
+    }
+
+    pub fn with_delay(mut self, delay: Duration) -> Self {
+        self.delay = delay;
+        self
+    }
+
+    pub fn with_gate(mut self, gate: Gate) -> Self {
+        self.gate = Some(gate);
+        self
+    }
+}
+
+/// Holds back the answers of fixtures until opened, so tests decide when a
+/// request finishes instead of relying on delays.
+///
+/// The gate opens by itself after some seconds, so a failing test doesn't
+/// hang waiting for it.
+#[derive(Debug, Clone, Default)]
+pub struct Gate(Arc<(Mutex<bool>, Condvar)>);
+
+impl Gate {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn open(&self) {
+        let (open, opened) = &*self.0;
+        *open.lock().unwrap() = true;
+        opened.notify_all();
+    }
+
+    fn wait(&self) {
+        let (open, opened) = &*self.0;
+        let open = open.lock().unwrap();
+        let _ = opened
+            .wait_timeout_while(open, GATE_TIMEOUT, |open| !*open)
+            .unwrap();
+    }
+}
+
+/// Local HTTP server answering GET requests from fixtures.
+///
+/// Requests to unknown paths are answered with 404. The server is
+/// shut down when dropped.
+pub struct StandInServer {
+    addr: SocketAddr,
+    shutdown: Arc<AtomicBool>,
+    requests: Arc<Mutex<HashMap<String, usize>>>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl StandInServer {
+    /// Starts the server on a free loopback port.
+    pub fn start(fixtures: HashMap<String, Fixture>) -> io::Result<Self> {
+        let sequences = fixtures
+            .into_iter()
+            .map(|(path, fixture)| (path, vec![fixture]))
+            .collect();
+        Self::start_sequences(sequences)
+    }
+
+    /// Starts the server answering the n-th request to a path with the n-th
+    /// fixture of its sequence, repeating the last one once exhausted.
+    pub fn start_sequences(fixtures: HashMap<String, Vec<Fixture>>) -> io::Result<Self> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let addr = listener.local_addr()?;
+        let shutdown = Arc::new(AtomicBool::new(false));
+        let requests = Arc::new(Mutex::new(HashMap::new()));
+        let fixtures = Arc::new(fixtures);
+
+        let handle = {
+            let shutdown = Arc::clone(&shutdown);
+            let requests = Arc::clone(&requests);
+            thread::spawn(move || {
+                for stream in listener.incoming() {
+                    if shutdown.load(Ordering::Acquire) {
+                        break;
+                    }
+                    let Ok(stream) = stream else { continue };
+                    let fixtures = Arc::clone(&fixtures);
+                    let requests = Arc::clone(&requests);
+                    thread::spawn(move || {
+                        if let Err(err) = serve(stream, &fixtures, &requests) {
+                            log::warn!("Stand-in server failed to answer request: {err}");
+                        }
+                    });
+                }
+            })
+        };
+
+        Ok(Self {
+            addr,
+            shutdown,
+            requests,
+            handle: Some(handle),
+        })
+    }
+
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Count of requests received for the path so far.
+    pub fn request_count(&self, path: &str) -> usize {
+        self.requests
+            .lock()
+            .unwrap()
+            .get(path)
+            .copied()
+            .unwrap_or_default()
+    }
+}
+
+impl Drop for StandInServer {
+    fn drop(&mut self) {
+        self.shutdown.store(true, Ordering::Release);
+        // Wake up the listener which is blocked waiting for connections.
+        let _ = TcpStream::connect(self.addr);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+fn serve(
+    stream: TcpStream,
+    fixtures: &HashMap<String, Vec<Fixture>>,
+    requests: &Mutex<HashMap<String, usize>>,
+) -> io::Result<()> {
+    let mut reader = BufReader::new(&stream);
+    let request_line = read_line(&mut reader)?;
+    // Request body isn't needed for GET requests.
+    read_headers(&mut reader)?;
+
+    let path = match request_line.split_whitespace().collect::<Vec<_>>()[..] {
+        ["GET", path, _version] => path,
+        _ => return respond(&stream, &Fixture::status(400)),
+    };
+
+    let index = {
+        let mut requests = requests.lock().unwrap();
+        let count = requests.entry(path.to_owned()).or_default();
+        *count += 1;
+        *count - 1
+    };
+
+    match fixtures
+        .get(path)
+        .and_then(|sequence| sequence.get(index).or(sequence.last()))
+    {
+        Some(fixture) => {
+            if let Some(gate) = &fixture.gate {
+                gate.wait();
+            }
+            thread::sleep(fixture.delay);
+            respond(&stream, fixture)
+        }
+        None => respond(&stream, &Fixture::status(404)),
+    }
+}
+
+fn respond(mut stream: &TcpStream, fixture: &Fixture) -> io::Result<()> {
+    let reason = match fixture.status {
+        200 => "OK",
+        400 => "Bad Request",
+        404 => "Not Found",
+        500 => "Internal Server Error",
+        503 => "Service Unavailable",
+        _ => "Unknown",
+    };
+
+    write!(
+        stream,
+        "HTTP/1.1 {} {reason}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        fixture.status,
+        fixture.body.len(),
+        fixture.body
+    )?;
+    stream.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_response() {
+        let raw = "HTTP/1.1 200 OK\r\ncontent-length: 2\r\n\r\n[]trailing";
+        let response = read_response(raw.as_bytes()).unwrap();
+        assert_eq!(response.status, 200);
+        assert_eq!(response.body, "[]");
+
+        let raw = "HTTP/1.0 503 Service Unavailable\r\n\r\nbody until end";
+        let response = read_response(raw.as_bytes()).unwrap();
+        assert_eq!(response.status, 503);
+        assert_eq!(response.body, "body until end");
+    }
+
+    #[test]
+    fn invalid_response() {
+        let err = read_response("SMTP 200\r\n\r\n".as_bytes()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        let err = read_response("HTTP/1.1 200 OK\r\nContent".as_bytes()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        let raw = format!("HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n[]", u64::MAX);
+        let err = read_response(raw.as_bytes()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        let err =
+            read_response("HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\n[]".as_bytes()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn header_limits() {
+        let long = "x".repeat(MAX_LINE_LENGTH as usize);
+        for raw in [
+            format!("HTTP/1.1 200 {long}\r\n\r\n"),
+            format!("HTTP/1.1 200 OK\r\nX-Long: {long}\r\n\r\n"),
+            // Without line break, the line would be read until the end.
+            format!("HTTP/1.1 200 OK\r\nX-Endless: {}", "x".repeat(1 << 20)),
+            format!(
+                "HTTP/1.1 200 OK\r\n{}\r\n",
+                "X-Many: 1\r\n".repeat(MAX_HEADERS + 1)
+            ),
+        ] {
+            let err = read_response(raw.as_bytes()).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        }
+
+        let raw = format!(
+            "HTTP/1.1 200 OK\r\n{}\r\n[]",
+            "X-Many: 1\r\n".repeat(MAX_HEADERS)
+        );
+        assert_eq!(read_response(raw.as_bytes()).unwrap().body, "[]");
+    }
+
+    #[test]
+    fn timeout_covers_whole_response() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            stream
+                .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 1000\r\n\r\n[")
+                .unwrap();
+            // Each byte arrives well within the timeout, the whole body doesn't.
+            while stream.write_all(b" ").is_ok() {
+                thread::sleep(Duration::from_millis(10));
+            }
+        });
+
+        let service = HttpRestService::new(addr).with_timeout(Duration::from_millis(200));
+        let start = Instant::now();
+        assert!(matches!(service.query("/data"), Err(QueryError::Timeout)));
+        assert!(start.elapsed() < Duration::from_secs(2));
+    }
+}
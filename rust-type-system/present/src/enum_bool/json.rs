@@ -0,0 +1,430 @@
+//! Minimal JSON support for exchanging [`Data`] without extra dependencies.
+
+use std::{fmt, iter::Peekable, str::CharIndices};
+
+use super::backend::Data;
+
+/// Maximum nesting of arrays and objects, which keeps the recursive parser
+/// from overflowing the stack on hostile input.
+pub const MAX_DEPTH: usize = 128;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    /// Object members in their original order.
+    Object(Vec<(String, Json)>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JsonError {
+    /// Byte offset in the input where the error is detected.
+    pub position: usize,
+    pub message: String,
+}
+
+impl JsonError {
+    fn new(position: usize, message: impl Into<String>) -> Self {
+        Self {
+            position,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for JsonError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Invalid JSON at {}: {}", self.position, self.message)
+    }
+}
+
+impl std::error::Error for JsonError {}
+
+impl Json {
+    pub fn parse(text: &str) -> Result<Json, JsonError> {
+        let mut parser = Parser {
+            text,
+            chars: text.char_indices().peekable(),
+            depth: 0,
+        };
+        let value = parser.value()?;
+        parser.skip_whitespace();
+        match parser.chars.next() {
+            Some((pos, _)) => Err(JsonError::new(pos, "Unexpected trailing characters")),
+            None => Ok(value),
+        }
+    }
+
+    /// Gets the member with the given key if this is an object.
+    pub fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            Json::Object(members) => members
+                .iter()
+                .find(|(name, _)| name == key)
+                .map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Json::String(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[Json]> {
+        match self {
+            Json::Array(items) => Some(items),
+            _ => None,
+        }
+    }
+
+    pub fn as_u64(&self) -> Option<u64> {
+        match self {
+            Json::Number(value) if value.fract() == 0.0 && *value >= 0.0 => Some(*value as u64),
+            _ => None,
+        }
+    }
+}
+
+fn write_string(f: &mut fmt::Formatter<'_>, value: &str) -> fmt::Result {
+    f.write_str("\"")?;
+    for ch in value.chars() {
+        match ch {
+            '"' => f.write_str("\\\"")?,
+            '\\' => f.write_str("\\\\")?,
+            '\n' => f.write_str("\\n")?,
+            '\r' => f.write_str("\\r")?,
+            '\t' => f.write_str("\\t")?,
+            ch if ch.is_control() => write!(f, "\\u{:04x}", ch as u32)?,
+            ch => write!(f, "{ch}")?,
+        }
+    }
+    f.write_str("\"")
+}
+
+/// Prints compact JSON.
+impl fmt::Display for Json {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Json::Null => f.write_str("null"),
+            Json::Bool(value) => write!(f, "{value}"),
+            Json::Number(value) => write!(f, "{value}"),
+            Json::String(value) => write_string(f, value),
+            Json::Array(items) => {
+                f.write_str("[")?;
+                for (idx, item) in items.iter().enumerate() {
+                    if idx > 0 {
+                        f.write_str(",")?;
+                    }
+                    write!(f, "{item}")?;
+                }
+                f.write_str("]")
+            }
+            Json::Object(members) => {
+                f.write_str("{")?;
+                for (idx, (key, value)) in members.iter().enumerate() {
+                    if idx > 0 {
+                        f.write_str(",")?;
+                    }
+                    write_string(f, key)?;
+                    write!(f, ":{value}")?;
+                }
+                f.write_str("}")
+            }
+        }
+    }
+}
+
+struct Parser<'a> {
+    text: &'a str,
+    chars: Peekable<CharIndices<'a>>,
+    /// Count of arrays and objects enclosing the current value.
+    depth: usize,
+}
+
+impl Parser<'_> {
+    fn skip_whitespace(&mut self) {
+        while self
+            .chars
+            .next_if(|(_, ch)| matches!(ch, ' ' | '\t' | '\n' | '\r'))
+            .is_some()
+        {}
+    }
+
+    fn position(&mut self) -> usize {
+        self.chars
+            .peek()
+            .map(|(pos, _)| *pos)
+            .unwrap_or(self.text.len())
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), JsonError> {
+        let pos = self.position();
+        match self.chars.next() {
+            Some((_, ch)) if ch == expected => Ok(()),
+            _ => Err(JsonError::new(pos, format!("Expected '{expected}'"))),
+        }
+    }
+
+    fn literal(&mut self, literal: &str, value: Json) -> Result<Json, JsonError> {
+        let pos = self.position();
+        for expected in literal.chars() {
+            if self.chars.next_if(|(_, ch)| *ch == expected).is_none() {
+                return Err(JsonError::new(pos, format!("Expected '{literal}'")));
+            }
+        }
+        Ok(value)
+    }
+
+    fn value(&mut self) -> Result<Json, JsonError> {
+        self.skip_whitespace();
+        let pos = self.position();
+        match self.chars.peek().map(|(_, ch)| *ch) {
+            Some('n') => self.literal("null", Json::Null),
+            Some('t') => self.literal("true", Json::Bool(true)),
+            Some('f') => self.literal("false", Json::Bool(false)),
+            Some('"') => self.string().map(Json::String),
+            Some('[') => self.nested(Self::array),
+            Some('{') => self.nested(Self::object),
+            Some(ch) if ch == '-' || ch.is_ascii_digit() => self.number(),
+            Some(_) => Err(JsonError::new(pos, "Unexpected character")),
+            None => Err(JsonError::new(pos, "Unexpected end of input")),
+        }
+    }
+
+    fn nested(
+        &mut self,
+        parse: fn(&mut Self) -> Result<Json, JsonError>,
+    ) -> Result<Json, JsonError> {
+        if self.depth == MAX_DEPTH {
+            let pos = self.position();
+            return Err(JsonError::new(pos, "Nesting too deep"));
+        }
+
+        self.depth += 1;
+        let value = parse(self);
+        self.depth -= 1;
+        value
+    }
+
+    fn number(&mut self) -> Result<Json, JsonError> {
+        let start = self.position();
+        while self
+            .chars
+            .next_if(|(_, ch)| matches!(ch, '-' | '+' | '.' | 'e' | 'E' | '0'..='9'))
+            .is_some()
+        {}
+        let end = self.position();
+
+        self.text[start..end]
+            .parse()
+            .map(Json::Number)
+            .map_err(|_| JsonError::new(start, "Invalid number"))
+    }
+
+    fn string(&mut self) -> Result<String, JsonError> {
+        self.expect('"')?;
+        let mut value = String::new();
+        loop {
+            let pos = self.position();
+            match self.chars.next() {
+                Some((_, '"')) => return Ok(value),
+                Some((_, '\\')) => value.push(self.escape()?),
+                // Other control characters like DEL are valid unescaped.
+                Some((_, ch)) if ch < '\u{20}' => {
+                    return Err(JsonError::new(pos, "Control character in string"));
+                }
+                Some((_, ch)) => value.push(ch),
+                None => return Err(JsonError::new(pos, "Unterminated string")),
+            }
+        }
+    }
+
+    fn escape(&mut self) -> Result<char, JsonError> {
+        let pos = self.position();
+        let ch = match self.chars.next().map(|(_, ch)| ch) {
+            Some('"') => '"',
+            Some('\\') => '\\',
+            Some('/') => '/',
+            Some('b') => '\u{8}',
+            Some('f') => '\u{c}',
+            Some('n') => '\n',
+            Some('r') => '\r',
+            Some('t') => '\t',
+            Some('u') => {
+                let mut code = self.hex()?;
+                if (0xD800..0xDC00).contains(&code) {
+                    // Characters outside the basic plane are encoded as surrogate pair.
+                    self.literal("\\u", Json::Null)?;
+                    let low = self.hex()?;
+                    if !(0xDC00..0xE000).contains(&low) {
+                        return Err(JsonError::new(pos, "Invalid surrogate pair"));
+                    }
+                    code = 0x10000 + ((code - 0xD800) << 10) + (low - 0xDC00);
+                }
+                char::from_u32(code).ok_or_else(|| JsonError::new(pos, "Invalid unicode escape"))?
+            }
+            _ => return Err(JsonError::new(pos, "Invalid escape")),
+        };
+        Ok(ch)
+    }
+
+    fn hex(&mut self) -> Result<u32, JsonError> {
+        let pos = self.position();
+        let mut value = 0;
+        for _ in 0..4 {
+            let digit = self
+                .chars
+                .next()
+                .and_then(|(_, ch)| ch.to_digit(16))
+                .ok_or_else(|| JsonError::new(pos, "Invalid unicode escape"))?;
+            value = value * 16 + digit;
+        }
+        Ok(value)
+    }
+
+    fn array(&mut self) -> Result<Json, JsonError> {
+        self.expect('[')?;
+        let mut items = Vec::new();
+        self.skip_whitespace();
+        if self.chars.next_if(|(_, ch)| *ch == ']').is_some() {
+            return Ok(Json::Array(items));
+        }
+
+        loop {
+            items.push(self.value()?);
+            self.skip_whitespace();
+            if self.chars.next_if(|(_, ch)| *ch == ']').is_some() {
+                return Ok(Json::Array(items));
+            }
+            self.expect(',')?;
+        }
+    }
+
+    fn object(&mut self) -> Result<Json, JsonError> {
+        self.expect('{')?;
+        let mut members = Vec::new();
+        self.skip_whitespace();
+        if self.chars.next_if(|(_, ch)| *ch == '}').is_some() {
+            return Ok(Json::Object(members));
+        }
+
+        loop {
+            self.skip_whitespace();
+            let key = self.string()?;
+            self.skip_whitespace();
+            self.expect(':')?;
+            members.push((key, self.value()?));
+            self.skip_whitespace();
+            if self.chars.next_if(|(_, ch)| *ch == '}').is_some() {
+                return Ok(Json::Object(members));
+            }
+            self.expect(',')?;
+        }
+    }
+}
+
+impl Data {
+    pub fn to_json(&self) -> Json {
+        Json::Object(vec![
+            ("id".into(), Json::String(self.id.clone())),
+            ("value".into(), Json::String(self.value.clone())),
+        ])
+    }
+
+    pub fn from_json(json: &Json) -> Result<Data, JsonError> {
+        let field = |name: &str| {
+            json.get(name)
+                .and_then(Json::as_str)
+                .ok_or_else(|| JsonError::new(0, format!("Data needs string field '{name}'")))
+        };
+
+        Ok(Data::new(field("id")?, field("value")?))
+    }
+}
+
+/// Encodes the items as JSON array.
+pub fn encode_data(data: &[Data]) -> String {
+    Json::Array(data.iter().map(Data::to_json).collect()).to_string()
+}
+
+/// Decodes the items from a JSON array.
+pub fn decode_data(text: &str) -> Result<Vec<Data>, JsonError> {
+    Json::parse(text)?
+        .as_array()
+        .ok_or_else(|| JsonError::new(0, "Expected array of data"))?
+        .iter()
+        .map(Data::from_json)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_values() {
+        let json =
+            Json::parse(r#" {"a": [1, -2.5e1, true, null], "b": "x\"yé\ud83d\ude00"} "#).unwrap();
+
+        assert_eq!(
+            json.get("a"),
+            Some(&Json::Array(vec![
+                Json::Number(1.0),
+                Json::Number(-25.0),
+                Json::Bool(true),
+                Json::Null
+            ]))
+        );
+        assert_eq!(json.get("b").and_then(Json::as_str), Some("x\"yé😀"));
+
+        let json = Json::parse("\"del \u{7f} next line \u{85}\"").unwrap();
+        assert_eq!(json.as_str(), Some("del \u{7f} next line \u{85}"));
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert!(Json::parse("").is_err());
+        assert!(Json::parse("[1, 2").is_err());
+        assert!(Json::parse("{\"a\" 1}").is_err());
+        assert!(Json::parse("\"unterminated").is_err());
+        assert!(Json::parse("[] []").is_err());
+        assert!(Json::parse("nul").is_err());
+        assert!(Json::parse("\"tab\tin string\"").is_err());
+    }
+
+    #[test]
+    fn nesting_limit() {
+        let nested = |depth| format!("{}{}", "[".repeat(depth), "]".repeat(depth));
+        assert!(Json::parse(&nested(MAX_DEPTH)).is_ok());
+
+        let err = Json::parse(&nested(MAX_DEPTH + 1)).unwrap_err();
+        assert_eq!(err.position, MAX_DEPTH);
+        // Deeper input fails the same way instead of overflowing the stack.
+        assert!(Json::parse(&"[{\"a\":".repeat(1_000_000)).is_err());
+    }
+
+    #[test]
+    fn data_round_trip() {
+        let data = vec![
+            Data::new("1", "first"),
+            Data::new("2", ""),
+            Data::new("3", "quote \" slash \\ line\n tab\t bell\u{7}"),
+        ];
+
+        let text = encode_data(&data);
+        assert_eq!(decode_data(&text), Ok(data));
+    }
+
+    #[test]
+    fn invalid_data() {
+        assert!(decode_data("{}").is_err());
+        assert!(decode_data(r#"[{"id": "1"}]"#).is_err());
+        assert!(decode_data(r#"[{"id": 1, "value": "x"}]"#).is_err());
+    }
+}
@@ -0,0 +1,310 @@
+//! Counters and latency histograms of the queries of a
+//! [`DataService`](super::good::DataService).
+
+use std::{collections::HashMap, fmt::Write, sync::Mutex, time::Duration};
+
+use super::good::DataSource;
+
+/// Upper bounds in seconds of the latency histogram buckets.
+pub const LATENCY_BUCKETS: [f64; 12] = [
+    0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0,
+];
+
+/// All sources in export order.
+const SOURCES: [DataSource; 5] = {
+    // Reminder to add new sources below
+    match DataSource::Cache {
+        DataSource::Cache => {}
+        DataSource::ApiService => {}
+        DataSource::CacheOrApi => {}
+        DataSource::ApiThenCache => {}
+        DataSource::Refresh => {}
+    }
+
+    [
+        DataSource::Cache,
+        DataSource::ApiService,
+        DataSource::CacheOrApi,
+        DataSource::ApiThenCache,
+        DataSource::Refresh,
+    ]
+};
+
+fn source_label(source: DataSource) -> &'static str {
+    match source {
+        DataSource::Cache => "cache",
+        DataSource::ApiService => "api_service",
+        DataSource::CacheOrApi => "cache_or_api",
+        DataSource::ApiThenCache => "api_then_cache",
+        DataSource::Refresh => "refresh",
+    }
+}
+
+/// All sources with their labels in export order.
+fn sources() -> impl Iterator<Item = (DataSource, &'static str)> {
+    SOURCES
+        .into_iter()
+        .map(|source| (source, source_label(source)))
+}
+
+/// Distribution of query latencies.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Histogram {
+    /// Count of observations per bucket of [`LATENCY_BUCKETS`], each
+    /// counted in the first bucket it fits in. Slower ones are only
+    /// part of `count`.
+    pub buckets: [u64; LATENCY_BUCKETS.len()],
+    pub count: u64,
+    pub sum: Duration,
+}
+
+impl Histogram {
+    fn observe(&mut self, latency: Duration) {
+        let seconds = latency.as_secs_f64();
+        if let Some(bucket) = LATENCY_BUCKETS.iter().position(|&bound| seconds <= bound) {
+            self.buckets[bucket] += 1;
+        }
+        self.count += 1;
+        self.sum += latency;
+    }
+
+    /// Count of observations up to each bucket bound.
+    pub fn cumulative(&self) -> impl Iterator<Item = (f64, u64)> + '_ {
+        LATENCY_BUCKETS
+            .iter()
+            .zip(self.buckets.iter().scan(0, |total, count| {
+                *total += count;
+                Some(*total)
+            }))
+            .map(|(&bound, count)| (bound, count))
+    }
+}
+
+/// Metrics of the queries using one data source.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SourceMetrics {
+    /// Queries which failed.
+    pub errors: u64,
+    /// Latency of all queries, failed ones included.
+    pub latency: Histogram,
+}
+
+/// Metrics at one point in time.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MetricsSnapshot {
+    /// Cache queries answered with data.
+    pub cache_hits: u64,
+    /// Cache queries without data, e.g. missing or expired.
+    pub cache_misses: u64,
+    /// Items removed by any step of the
+    /// [`EmptyDataOptions`](super::good::EmptyDataOptions), not only by
+    /// ignoring empty items.
+    pub filtered_items: u64,
+    /// Queries which joined a running fetch of the same data instead of
+    /// fetching it themselves.
+    pub coalesced_queries: u64,
+    pub sources: HashMap<DataSource, SourceMetrics>,
+}
+
+impl MetricsSnapshot {
+    /// Fraction of the cache queries which were hits, if any were made.
+    pub fn cache_hit_ratio(&self) -> Option<f64> {
+        let total = self.cache_hits + self.cache_misses;
+        (total > 0).then(|| self.cache_hits as f64 / total as f64)
+    }
+
+    pub fn source(&self, source: DataSource) -> SourceMetrics {
+        self.sources.get(&source).cloned().unwrap_or_default()
+    }
+
+    /// Formats the metrics in the Prometheus text exposition format, to be
+    /// served by a metrics endpoint.
+    pub fn to_prometheus(&self) -> String {
+        let mut out = String::new();
+        // Writing to a string doesn't fail.
+        let _ = self.write_prometheus(&mut out);
+        out
+    }
+
+    fn write_prometheus(&self, out: &mut String) -> std::fmt::Result {
+        for (name, help, value) in [
+            (
+                "cache_hits",
+                "Cache queries answered with data.",
+                self.cache_hits,
+            ),
+            (
+                "cache_misses",
+                "Cache queries without data.",
+                self.cache_misses,
+            ),
+            (
+                "filtered_items",
+                "Items removed by the filters of the queries.",
+                self.filtered_items,
+            ),
+            (
+                "coalesced_queries",
+                "Queries which joined a running fetch of the same data.",
+                self.coalesced_queries,
+            ),
+        ] {
+            writeln!(out, "# HELP data_service_{name}_total {help}")?;
+            writeln!(out, "# TYPE data_service_{name}_total counter")?;
+            writeln!(out, "data_service_{name}_total {value}")?;
+        }
+
+        writeln!(out, "# HELP data_service_errors_total Failed queries.")?;
+        writeln!(out, "# TYPE data_service_errors_total counter")?;
+        for (source, label) in sources() {
+            let errors = self.source(source).errors;
+            writeln!(
+                out,
+                "data_service_errors_total{{source=\"{label}\"}} {errors}"
+            )?;
+        }
+
+        let name = "data_service_query_duration_seconds";
+        writeln!(out, "# HELP {name} Latency of the queries.")?;
+        writeln!(out, "# TYPE {name} histogram")?;
+        for (source, label) in sources() {
+            let latency = self.source(source).latency;
+            for (bound, count) in latency.cumulative() {
+                writeln!(
+                    out,
+                    "{name}_bucket{{source=\"{label}\",le=\"{bound}\"}} {count}"
+                )?;
+            }
+            let count = latency.count;
+            writeln!(
+                out,
+                "{name}_bucket{{source=\"{label}\",le=\"+Inf\"}} {count}"
+            )?;
+            let sum = latency.sum.as_secs_f64();
+            writeln!(out, "{name}_sum{{source=\"{label}\"}} {sum}")?;
+            writeln!(out, "{name}_count{{source=\"{label}\"}} {count}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Metrics recorded while querying.
+#[derive(Debug, Default)]
+pub struct Metrics {
+    snapshot: Mutex<MetricsSnapshot>,
+}
+
+impl Metrics {
+    pub fn snapshot(&self) -> MetricsSnapshot {
+        self.snapshot.lock().unwrap().clone()
+    }
+
+    pub(super) fn record_cache(&self, hit: bool) {
+        let mut snapshot = self.snapshot.lock().unwrap();
+        if hit {
+            snapshot.cache_hits += 1;
+        } else {
+            snapshot.cache_misses += 1;
+        }
+    }
+
+    pub(super) fn record_filtered(&self, count: usize) {
+        self.snapshot.lock().unwrap().filtered_items += count as u64;
+    }
+
+    pub(super) fn record_coalesced(&self) {
+        self.snapshot.lock().unwrap().coalesced_queries += 1;
+    }
+
+    pub(super) fn record_query(&self, source: DataSource, latency: Duration, failed: bool) {
+        let mut snapshot = self.snapshot.lock().unwrap();
+        let metrics = snapshot.sources.entry(source).or_default();
+        metrics.latency.observe(latency);
+        if failed {
+            metrics.errors += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn histogram() {
+        let mut histogram = Histogram::default();
+        histogram.observe(Duration::from_micros(500));
+        histogram.observe(Duration::from_millis(20));
+        histogram.observe(Duration::from_millis(25));
+        histogram.observe(Duration::from_secs(60));
+
+        assert_eq!(histogram.count, 4);
+        assert_eq!(histogram.sum, Duration::from_micros(60_045_500));
+        let cumulative: Vec<_> = histogram.cumulative().map(|(_, count)| count).collect();
+        assert_eq!(cumulative, [1, 1, 1, 1, 3, 3, 3, 3, 3, 3, 3, 3]);
+    }
+
+    #[test]
+    fn hit_ratio() {
+        let metrics = Metrics::default();
+        assert_eq!(metrics.snapshot().cache_hit_ratio(), None);
+
+        metrics.record_cache(true);
+        metrics.record_cache(true);
+        metrics.record_cache(true);
+        metrics.record_cache(false);
+        assert_eq!(metrics.snapshot().cache_hit_ratio(), Some(0.75));
+    }
+
+    #[test]
+    fn all_sources() {
+        let labels: Vec<_> = sources().map(|(_, label)| label).collect();
+        assert_eq!(
+            labels,
+            [
+                "cache",
+                "api_service",
+                "cache_or_api",
+                "api_then_cache",
+                "refresh"
+            ]
+        );
+        // Each source is exported once.
+        for (index, source) in SOURCES.iter().enumerate() {
+            assert!(!SOURCES[..index].contains(source), "{source:?}");
+        }
+    }
+
+    #[test]
+    fn prometheus_export() {
+        let metrics = Metrics::default();
+        metrics.record_cache(true);
+        metrics.record_filtered(2);
+        metrics.record_query(DataSource::Cache, Duration::from_millis(3), false);
+        metrics.record_query(DataSource::ApiService, Duration::from_secs(10), true);
+
+        let text = metrics.snapshot().to_prometheus();
+        for line in [
+            "# TYPE data_service_cache_hits_total counter",
+            "data_service_cache_hits_total 1",
+            "data_service_cache_misses_total 0",
+            "data_service_filtered_items_total 2",
+            "data_service_coalesced_queries_total 0",
+            "data_service_errors_total{source=\"api_service\"} 1",
+            "data_service_errors_total{source=\"refresh\"} 0",
+            "# TYPE data_service_query_duration_seconds histogram",
+            "data_service_query_duration_seconds_bucket{source=\"cache\",le=\"0.0025\"} 0",
+            "data_service_query_duration_seconds_bucket{source=\"cache\",le=\"0.005\"} 1",
+            "data_service_query_duration_seconds_bucket{source=\"cache\",le=\"+Inf\"} 1",
+            "data_service_query_duration_seconds_sum{source=\"cache\"} 0.003",
+            "data_service_query_duration_seconds_bucket{source=\"api_service\",le=\"5\"} 0",
+            "data_service_query_duration_seconds_bucket{source=\"api_service\",le=\"+Inf\"} 1",
+            "data_service_query_duration_seconds_count{source=\"api_service\"} 1",
+        ] {
+            assert!(
+                text.lines().any(|l| l == line),
+                "missing {line:?} in:\n{text}"
+            );
+        }
+    }
+}
@@ -0,0 +1,547 @@
+//! Recording of REST responses to fixture files and their replay, so code
+//! querying the API can be tested offline and deterministically.
+//!
+//! A fixture file lists the recorded interactions in request order, one per
+//! line:
+//!
+//! ```json
+//! {"version":1,"interactions":[
+//! {"key":"/data","data":[{"id":"sensor-1","value":"21.5"}]},
+//! {"key":"/data?page=2","error":{"kind":"http","status":503}}
+//! ]}
+//! ```
+
+use std::{
+    collections::HashMap,
+    fs, io,
+    path::{Path, PathBuf},
+    sync::Mutex,
+    time::Duration,
+};
+
+use super::{
+    backend::{Data, DataSourceBackend, QueryError, RestService},
+    file_cache::write_atomically,
+    json::Json,
+};
+
+/// Version of the fixture files.
+pub const FIXTURE_VERSION: u64 = 1;
+
+type Response = Result<Vec<Data>, QueryError>;
+
+fn invalid_data(msg: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.into())
+}
+
+fn encode_error(err: &QueryError) -> Option<Json> {
+    let (kind, detail) = match err {
+        QueryError::NotFound(key) => ("not_found", Some(("key", Json::String(key.clone())))),
+        QueryError::Backend(msg) => ("backend", Some(("message", Json::String(msg.clone())))),
+        QueryError::Http(status) => ("http", Some(("status", Json::Number(*status as f64)))),
+        QueryError::Timeout => ("timeout", None),
+        QueryError::Io(msg) => ("io", Some(("message", Json::String(msg.clone())))),
+        QueryError::InvalidResponse(msg) => (
+            "invalid_response",
+            Some(("message", Json::String(msg.clone()))),
+        ),
+        QueryError::CircuitOpen => ("circuit_open", None),
+        // Rejected before reaching any backend, so it isn't a response.
+        QueryError::InvalidOptions(_) => return None,
+    };
+
+    let mut members = vec![("kind".into(), Json::String(kind.into()))];
+    members.extend(detail.map(|(name, value)| (name.into(), value)));
+    Some(Json::Object(members))
+}
+
+fn decode_error(json: &Json) -> io::Result<QueryError> {
+    let string = |name: &str| {
+        json.get(name)
+            .and_then(Json::as_str)
+            .map(str::to_owned)
+            .ok_or_else(|| invalid_data(format!("Error needs string field '{name}'")))
+    };
+
+    match json.get("kind").and_then(Json::as_str) {
+        Some("not_found") => Ok(QueryError::NotFound(string("key")?)),
+        Some("backend") => Ok(QueryError::Backend(string("message")?)),
+        Some("http") => json
+            .get("status")
+            .and_then(Json::as_u64)
+            .and_then(|status| u16::try_from(status).ok())
+            .map(QueryError::Http)
+            .ok_or_else(|| invalid_data("Invalid HTTP status")),
+        Some("timeout") => Ok(QueryError::Timeout),
+        Some("io") => Ok(QueryError::Io(string("message")?)),
+        Some("invalid_response") => Ok(QueryError::InvalidResponse(string("message")?)),
+        Some("circuit_open") => Ok(QueryError::CircuitOpen),
+        kind => Err(invalid_data(format!("Unknown error kind {kind:?}"))),
+    }
+}
+
+fn encode_interaction(key: &str, response: &Response) -> Option<String> {
+    let response = match response {
+        Ok(data) => (
+            "data".into(),
+            Json::Array(data.iter().map(Data::to_json).collect()),
+        ),
+        Err(err) => ("error".into(), encode_error(err)?),
+    };
+    let interaction = Json::Object(vec![("key".into(), Json::String(key.into())), response]);
+    Some(interaction.to_string())
+}
+
+fn decode_interaction(json: &Json) -> io::Result<(String, Response)> {
+    let key = json
+        .get("key")
+        .and_then(Json::as_str)
+        .ok_or_else(|| invalid_data("Interaction needs a key"))?;
+    let response = match (json.get("data"), json.get("error")) {
+        (Some(data), None) => Ok(data
+            .as_array()
+            .ok_or_else(|| invalid_data("Data must be an array"))?
+            .iter()
+            .map(Data::from_json)
+            .collect::<Result<_, _>>()
+            .map_err(|err| invalid_data(err.to_string()))?),
+        (None, Some(error)) => Err(decode_error(error)?),
+        _ => return Err(invalid_data("Interaction needs either data or error")),
+    };
+    Ok((key.to_owned(), response))
+}
+
+/// Reads the interactions of the fixture file in their recorded order.
+fn read_fixture(path: &Path) -> io::Result<Vec<(String, Response)>> {
+    let text = fs::read_to_string(path)?;
+    let json = Json::parse(&text).map_err(|err| invalid_data(err.to_string()))?;
+    match json.get("version").and_then(Json::as_u64) {
+        Some(FIXTURE_VERSION) => {}
+        version => {
+            return Err(invalid_data(format!(
+                "Unsupported fixture version {version:?}"
+            )))
+        }
+    }
+
+    json.get("interactions")
+        .and_then(Json::as_array)
+        .ok_or_else(|| invalid_data("Fixture needs interactions"))?
+        .iter()
+        .map(decode_interaction)
+        .collect()
+}
+
+/// REST service writing the responses of the wrapped service to a fixture
+/// file, which can be served by [`ReplayRestService`] afterwards.
+///
+/// The fixture is rewritten after each request, keeping the interactions
+/// it contained already.
+///
+/// # Panics
+///
+/// Queries panic if the fixture can't be written, since the recording would
+/// silently miss the interaction otherwise.
+#[derive(Debug)]
+pub struct RecordingRestService<R> {
+    rest_api: R,
+    path: PathBuf,
+    /// Encoded interactions in request order.
+    interactions: Mutex<Vec<String>>,
+}
+
+impl<R: RestService> RecordingRestService<R> {
+    /// Records to the fixture file at the path, appending to it if it exists.
+    pub fn new(rest_api: R, path: impl Into<PathBuf>) -> io::Result<Self> {
+        let path = path.into();
+        let interactions = match read_fixture(&path) {
+            Ok(interactions) => interactions
+                .iter()
+                .filter_map(|(key, response)| encode_interaction(key, response))
+                .collect(),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Vec::new(),
+            Err(err) => return Err(err),
+        };
+
+        Ok(Self {
+            rest_api,
+            path,
+            interactions: Mutex::new(interactions),
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn record(&self, key: &str, response: &Response) {
+        let Some(interaction) = encode_interaction(key, response) else {
+            return;
+        };
+
+        let mut interactions = self.interactions.lock().unwrap();
+        interactions.push(interaction);
+        let contents = format!(
+            "{{\"version\":{FIXTURE_VERSION},\"interactions\":[\n{}\n]}}\n",
+            interactions.join(",\n")
+        );
+        if let Err(err) = write_atomically(&self.path, &contents) {
+            panic!("Failed to write fixture {}: {err}", self.path.display());
+        }
+    }
+}
+
+impl<R: RestService> DataSourceBackend for RecordingRestService<R> {
+    fn query(&self, key: &str) -> Result<Vec<Data>, QueryError> {
+        let response = self.rest_api.query(key);
+        self.record(key, &response);
+        response
+    }
+
+    fn query_with_timeout(&self, key: &str, timeout: Duration) -> Result<Vec<Data>, QueryError> {
+        let response = self.rest_api.query_with_timeout(key, timeout);
+        self.record(key, &response);
+        response
+    }
+}
+
+impl<R: RestService> RestService for RecordingRestService<R> {}
+
+/// REST service which can't be queried, used by [`ReplayRestService`]
+/// without recording of missing responses.
+#[derive(Debug)]
+pub enum NoRestService {}
+
+impl DataSourceBackend for NoRestService {
+    fn query(&self, _key: &str) -> Result<Vec<Data>, QueryError> {
+        match *self {}
+    }
+}
+
+impl RestService for NoRestService {}
+
+#[derive(Debug)]
+struct Replayed {
+    responses: Vec<Response>,
+    /// Index of the response to the next request.
+    next: usize,
+}
+
+/// REST service answering from the responses of a fixture file.
+///
+/// Responses recorded for the same key are replayed in order, repeating
+/// the last one once all were replayed.
+///
+/// # Panics
+///
+/// Queries panic for keys without recorded responses, unless missing
+/// responses are recorded, so tests notice requests which weren't expected.
+#[derive(Debug)]
+pub struct ReplayRestService<R = NoRestService> {
+    path: PathBuf,
+    replayed: Mutex<HashMap<String, Replayed>>,
+    recorder: Option<RecordingRestService<R>>,
+}
+
+impl ReplayRestService {
+    /// Replays the fixture file at the path.
+    pub fn open(path: impl Into<PathBuf>) -> io::Result<Self> {
+        let path = path.into();
+        let replayed = load_replayed(&path)?;
+        Ok(Self {
+            path,
+            replayed: Mutex::new(replayed),
+            recorder: None,
+        })
+    }
+}
+
+impl<R: RestService> ReplayRestService<R> {
+    /// Replays the fixture file at the path, querying the given service for
+    /// keys without recorded responses and recording its responses.
+    pub fn record_missing(rest_api: R, path: impl Into<PathBuf>) -> io::Result<Self> {
+        let path = path.into();
+        let replayed = match load_replayed(&path) {
+            Err(err) if err.kind() == io::ErrorKind::NotFound => HashMap::new(),
+            replayed => replayed?,
+        };
+        Ok(Self {
+            recorder: Some(RecordingRestService::new(rest_api, &path)?),
+            path,
+            replayed: Mutex::new(replayed),
+        })
+    }
+}
+
+fn load_replayed(path: &Path) -> io::Result<HashMap<String, Replayed>> {
+    let mut replayed = HashMap::<String, Replayed>::new();
+    for (key, response) in read_fixture(path)? {
+        replayed
+            .entry(key)
+            .or_insert_with(|| Replayed {
+                responses: Vec::new(),
+                next: 0,
+            })
+            .responses
+            .push(response);
+    }
+    Ok(replayed)
+}
+
+impl<R: RestService> ReplayRestService<R> {
+    /// Replays the response for the key, or queries the API with the timeout
+    /// if any when recording missing responses.
+    fn respond(&self, key: &str, timeout: Option<Duration>) -> Response {
+        // The lock is released before panicking or querying the API, so a
+        // missing recording neither poisons it nor blocks other callers.
+        let mut keys: Vec<_> = {
+            let mut replayed = self.replayed.lock().unwrap();
+            if let Some(replayed) = replayed.get_mut(key) {
+                let response = &replayed.responses[replayed.next];
+                replayed.next = (replayed.next + 1).min(replayed.responses.len() - 1);
+                return response.clone();
+            }
+            replayed.keys().cloned().collect()
+        };
+
+        let Some(recorder) = &self.recorder else {
+            keys.sort();
+            panic!(
+                "No recorded response for '{key}' in {}, recorded keys are {keys:?}",
+                self.path.display()
+            );
+        };
+        let response = match timeout {
+            Some(timeout) => recorder.query_with_timeout(key, timeout),
+            None => recorder.query(key),
+        };
+        // Keeps the response of a concurrent caller which recorded first.
+        self.replayed
+            .lock()
+            .unwrap()
+            .entry(key.to_owned())
+            .or_insert_with(|| Replayed {
+                responses: vec![response.clone()],
+                next: 0,
+            });
+        response
+    }
+}
+
+impl<R: RestService> DataSourceBackend for ReplayRestService<R> {
+    fn query(&self, key: &str) -> Result<Vec<Data>, QueryError> {
+        self.respond(key, None)
+    }
+
+    fn query_with_timeout(&self, key: &str, timeout: Duration) -> Result<Vec<Data>, QueryError> {
+        self.respond(key, Some(timeout))
+    }
+}
+
+impl<R: RestService> RestService for ReplayRestService<R> {}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::HashMap,
+        env,
+        panic::{self, AssertUnwindSafe},
+    };
+
+    use super::*;
+    use crate::enum_bool::{
+        backend::InMemoryRestService,
+        http::{Fixture, HttpRestService, StandInServer},
+    };
+
+    /// Fixture path in the temporary directory, removed when dropped.
+    struct TestFixture(PathBuf);
+
+    impl TestFixture {
+        fn new(name: &str) -> Self {
+            let path =
+                env::temp_dir().join(format!("present-replay-{}-{name}.json", std::process::id()));
+            let _ = fs::remove_file(&path);
+            Self(path)
+        }
+    }
+
+    impl Drop for TestFixture {
+        fn drop(&mut self) {
+            let _ = fs::remove_file(&self.0);
+        }
+    }
+
+    fn data() -> Vec<Data> {
+        vec![Data::new("sensor-1", "21.5"), Data::new("sensor-2", "")]
+    }
+
+    #[test]
+    fn record_and_replay() {
+        let fixture = TestFixture::new("record");
+        let server = StandInServer::start_sequences(HashMap::from([
+            ("/data".to_owned(), vec![Fixture::data(&data())]),
+            (
+                "/flaky".to_owned(),
+                vec![Fixture::status(503), Fixture::data(&data())],
+            ),
+        ]))
+        .unwrap();
+        let recording =
+            RecordingRestService::new(HttpRestService::new(server.addr()), &fixture.0).unwrap();
+
+        assert_eq!(recording.query("/data"), Ok(data()));
+        assert_eq!(recording.query("/flaky"), Err(QueryError::Http(503)));
+        assert_eq!(recording.query("/flaky"), Ok(data()));
+        assert_eq!(
+            recording.query("/missing"),
+            Err(QueryError::NotFound("/missing".into()))
+        );
+        drop(server);
+
+        let replay = ReplayRestService::open(&fixture.0).unwrap();
+        assert_eq!(replay.query("/data"), Ok(data()));
+        assert_eq!(replay.query("/data"), Ok(data()));
+        assert_eq!(replay.query("/flaky"), Err(QueryError::Http(503)));
+        assert_eq!(replay.query("/flaky"), Ok(data()));
+        assert_eq!(replay.query("/flaky"), Ok(data()));
+        assert_eq!(
+            replay.query("/missing"),
+            Err(QueryError::NotFound("/missing".into()))
+        );
+    }
+
+    #[test]
+    fn fixture_format() {
+        let fixture = TestFixture::new("format");
+        let rest_api = InMemoryRestService::new()
+            .respond("/data", Ok(data()))
+            .respond("/error", Err(QueryError::Io("reset".into())));
+        let recording = RecordingRestService::new(rest_api, &fixture.0).unwrap();
+        recording.query("/data").unwrap();
+        recording.query("/error").unwrap_err();
+
+        assert_eq!(
+            fs::read_to_string(&fixture.0).unwrap(),
+            r#"{"version":1,"interactions":[
+{"key":"/data","data":[{"id":"sensor-1","value":"21.5"},{"id":"sensor-2","value":""}]},
+{"key":"/error","error":{"kind":"io","message":"reset"}}
+]}
+"#
+        );
+    }
+
+    #[test]
+    fn error_round_trip() {
+        for err in [
+            QueryError::NotFound("/data".into()),
+            QueryError::Backend("down".into()),
+            QueryError::Http(429),
+            QueryError::Timeout,
+            QueryError::Io("reset".into()),
+            QueryError::InvalidResponse("not json".into()),
+            QueryError::CircuitOpen,
+        ] {
+            let json = encode_error(&err).unwrap();
+            assert_eq!(decode_error(&json).unwrap(), err);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "No recorded response for '/other'")]
+    fn unmatched_request() {
+        let fixture = TestFixture::new("unmatched");
+        let rest_api = InMemoryRestService::new().respond("/data", Ok(data()));
+        RecordingRestService::new(rest_api, &fixture.0)
+            .unwrap()
+            .query("/data")
+            .unwrap();
+
+        let replay = ReplayRestService::open(&fixture.0).unwrap();
+        let _ = replay.query("/other");
+    }
+
+    #[test]
+    fn replay_after_unmatched_request() {
+        let fixture = TestFixture::new("unmatched-after");
+        let rest_api = InMemoryRestService::new().respond("/data", Ok(data()));
+        RecordingRestService::new(rest_api, &fixture.0)
+            .unwrap()
+            .query("/data")
+            .unwrap();
+
+        let replay = ReplayRestService::open(&fixture.0).unwrap();
+        let unmatched = panic::catch_unwind(AssertUnwindSafe(|| replay.query("/other")));
+        assert!(unmatched.is_err());
+        assert_eq!(replay.query("/data"), Ok(data()));
+    }
+
+    #[test]
+    fn record_missing() {
+        let fixture = TestFixture::new("missing");
+        let rest_api = InMemoryRestService::new().respond("/data", Ok(data()));
+        RecordingRestService::new(rest_api, &fixture.0)
+            .unwrap()
+            .query("/data")
+            .unwrap();
+
+        let rest_api = InMemoryRestService::new()
+            .respond("/data", Ok(Vec::new()))
+            .respond("/other", Ok(data()));
+        let replay = ReplayRestService::record_missing(rest_api, &fixture.0).unwrap();
+        // Recorded responses are preferred over the API.
+        assert_eq!(replay.query("/data"), Ok(data()));
+        assert_eq!(replay.query("/other"), Ok(data()));
+
+        let replay = ReplayRestService::open(&fixture.0).unwrap();
+        assert_eq!(replay.query("/data"), Ok(data()));
+        assert_eq!(replay.query("/other"), Ok(data()));
+    }
+
+    #[test]
+    fn record_missing_with_timeout() {
+        let fixture = TestFixture::new("missing-timeout");
+        let server = StandInServer::start(HashMap::from([(
+            "/data".to_owned(),
+            Fixture::data(&data()).with_delay(Duration::from_millis(500)),
+        )]))
+        .unwrap();
+        let rest_api = HttpRestService::new(server.addr());
+        let replay = ReplayRestService::record_missing(rest_api, &fixture.0).unwrap();
+
+        assert_eq!(
+            replay.query_with_timeout("/data", Duration::from_millis(50)),
+            Err(QueryError::Timeout)
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Failed to write fixture")]
+    fn unwritable_fixture() {
+        let fixture = TestFixture::new("unwritable");
+        let path = fixture.0.join("fixture.json");
+        let rest_api = InMemoryRestService::new().respond("/data", Ok(data()));
+        let recording = RecordingRestService::new(rest_api, path).unwrap();
+        let _ = recording.query("/data");
+    }
+
+    #[test]
+    fn invalid_fixtures() {
+        let fixture = TestFixture::new("invalid");
+        assert_eq!(
+            ReplayRestService::open(&fixture.0).unwrap_err().kind(),
+            io::ErrorKind::NotFound
+        );
+
+        for contents in [
+            "not json",
+            r#"{"version":2,"interactions":[]}"#,
+            r#"{"version":1,"interactions":[{"key":"/data"}]}"#,
+            r#"{"version":1,"interactions":[{"key":"/data","error":{"kind":"gone"}}]}"#,
+        ] {
+            fs::write(&fixture.0, contents).unwrap();
+            let err = ReplayRestService::open(&fixture.0).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData, "{contents}");
+        }
+    }
+}
@@ -0,0 +1,457 @@
+//! [`RestService`] wrapper retrying failed requests and rejecting requests
+//! to a failing backend with a circuit breaker.
+
+use std::{
+    collections::hash_map::RandomState,
+    hash::{BuildHasher, Hasher},
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use super::{
+    backend::{Data, DataSourceBackend, QueryError, RestService},
+    clock::{Clock, SystemClock},
+};
+
+/// How failed requests are repeated.
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    /// Maximum count of requests per query, including the first one.
+    pub max_attempts: u32,
+    /// Delay before the first retry, doubled for each further retry.
+    pub initial_backoff: Duration,
+    /// Upper bound of the delay between retries.
+    pub max_backoff: Duration,
+    /// Fraction of the delay which is randomized, between 0 and 1, so
+    /// clients failing at the same time don't retry at the same time.
+    /// Values outside of that range are clamped to it.
+    pub jitter: f64,
+    /// Errors which are worth retrying, and count as failures for the
+    /// circuit breaker.
+    pub retry_on: fn(&QueryError) -> bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(10),
+            jitter: 0.5,
+            retry_on: QueryError::is_transient,
+        }
+    }
+}
+
+impl RetryPolicy {
+    pub fn with_max_attempts(mut self, max_attempts: u32) -> Self {
+        self.max_attempts = max_attempts;
+        self
+    }
+
+    pub fn with_backoff(mut self, initial: Duration, max: Duration) -> Self {
+        self.initial_backoff = initial;
+        self.max_backoff = max;
+        self
+    }
+
+    pub fn with_jitter(mut self, jitter: f64) -> Self {
+        self.jitter = jitter.clamp(0.0, 1.0);
+        self
+    }
+
+    pub fn with_retry_on(mut self, retry_on: fn(&QueryError) -> bool) -> Self {
+        self.retry_on = retry_on;
+        self
+    }
+
+    /// Delay before the given retry, starting at 0, without jitter.
+    pub fn backoff(&self, retry: u32) -> Duration {
+        self.initial_backoff
+            .saturating_mul(2u32.saturating_pow(retry))
+            .min(self.max_backoff)
+    }
+
+    /// Randomly shortens the backoff by up to the jitter fraction.
+    fn delay(&self, retry: u32) -> Duration {
+        // The field may be set directly, so it isn't necessarily clamped.
+        let jitter = if self.jitter.is_nan() {
+            0.0
+        } else {
+            self.jitter.clamp(0.0, 1.0)
+        };
+        self.backoff(retry)
+            .mul_f64(1.0 - jitter * random_fraction())
+    }
+}
+
+/// Random number in `[0, 1)`, good enough to spread retries.
+fn random_fraction() -> f64 {
+    // Each `RandomState` is seeded differently.
+    let bits = RandomState::new().build_hasher().finish();
+    (bits >> 11) as f64 / (1u64 << 53) as f64
+}
+
+/// When requests to a failing backend are rejected without sending them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CircuitBreakerPolicy {
+    /// Count of consecutive failures opening the circuit.
+    pub failure_threshold: u32,
+    /// Time the circuit stays open before a trial request is let through.
+    /// The circuit stays open for good when the end of the cooldown is
+    /// beyond the range of `Instant`.
+    pub cooldown: Duration,
+}
+
+impl Default for CircuitBreakerPolicy {
+    fn default() -> Self {
+        Self {
+            failure_threshold: 5,
+            cooldown: Duration::from_secs(30),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CircuitState {
+    /// Requests are sent.
+    Closed,
+    /// Requests are rejected until the cooldown is over.
+    Open,
+    /// Cooldown is over and the next request decides whether the circuit
+    /// closes or opens again.
+    HalfOpen,
+}
+
+#[derive(Debug)]
+enum Circuit {
+    Closed {
+        failures: u32,
+    },
+    Open {
+        /// End of the cooldown, `None` if it can't be represented.
+        until: Option<Instant>,
+    },
+    /// A trial request is running after the cooldown.
+    HalfOpen,
+}
+
+/// REST service repeating failed requests of the wrapped service.
+///
+/// Retries are delayed with exponential backoff. With a circuit breaker,
+/// requests are rejected with [`QueryError::CircuitOpen`] after too many
+/// consecutive failures, until a trial request succeeds after the cooldown.
+#[derive(Debug)]
+pub struct RetryingRestService<R, C = SystemClock> {
+    rest_api: R,
+    retry: RetryPolicy,
+    breaker: Option<CircuitBreakerPolicy>,
+    circuit: Mutex<Circuit>,
+    clock: C,
+}
+
+impl<R: RestService> RetryingRestService<R> {
+    pub fn new(rest_api: R, retry: RetryPolicy) -> Self {
+        Self::with_clock(rest_api, retry, SystemClock)
+    }
+}
+
+impl<R: RestService, C: Clock> RetryingRestService<R, C> {
+    /// Creates a service using the given clock to wait between retries and
+    /// for the cooldown.
+    pub fn with_clock(rest_api: R, retry: RetryPolicy, clock: C) -> Self {
+        Self {
+            rest_api,
+            retry,
+            breaker: None,
+            circuit: Mutex::new(Circuit::Closed { failures: 0 }),
+            clock,
+        }
+    }
+
+    pub fn with_circuit_breaker(mut self, policy: CircuitBreakerPolicy) -> Self {
+        self.breaker = Some(policy);
+        self
+    }
+
+    pub fn circuit_state(&self) -> CircuitState {
+        match *self.circuit.lock().unwrap() {
+            Circuit::Closed { .. } => CircuitState::Closed,
+            Circuit::Open { until: Some(until) } if until <= self.clock.now() => {
+                CircuitState::HalfOpen
+            }
+            Circuit::Open { .. } => CircuitState::Open,
+            Circuit::HalfOpen => CircuitState::HalfOpen,
+        }
+    }
+
+    fn send(
+        &self,
+        request: impl Fn() -> Result<Vec<Data>, QueryError>,
+    ) -> Result<Vec<Data>, QueryError> {
+        let mut last_error = None;
+        for retry in 0..self.retry.max_attempts.max(1) {
+            if retry > 0 {
+                self.clock.sleep(self.retry.delay(retry - 1));
+            }
+            if let Err(err) = self.acquire() {
+                // The upstream error explains why the circuit opened.
+                return Err(last_error.unwrap_or(err));
+            }
+
+            let result = request();
+            let failed = result.as_ref().is_err_and(|err| (self.retry.retry_on)(err));
+            self.record(failed);
+            match result {
+                Err(err) if failed => last_error = Some(err),
+                result => return result,
+            }
+        }
+
+        Err(last_error.expect("at least one request is sent"))
+    }
+
+    /// Checks if the circuit lets a request through.
+    fn acquire(&self) -> Result<(), QueryError> {
+        if self.breaker.is_none() {
+            return Ok(());
+        }
+
+        let mut circuit = self.circuit.lock().unwrap();
+        match *circuit {
+            Circuit::Closed { .. } => Ok(()),
+            Circuit::Open { until: Some(until) } if until <= self.clock.now() => {
+                *circuit = Circuit::HalfOpen;
+                Ok(())
+            }
+            Circuit::Open { .. } | Circuit::HalfOpen => Err(QueryError::CircuitOpen),
+        }
+    }
+
+    fn record(&self, failed: bool) {
+        let Some(policy) = self.breaker else {
+            return;
+        };
+
+        let mut circuit = self.circuit.lock().unwrap();
+        let open = Circuit::Open {
+            until: self.clock.now().checked_add(policy.cooldown),
+        };
+        *circuit = match *circuit {
+            _ if !failed => Circuit::Closed { failures: 0 },
+            Circuit::Closed { failures } if failures + 1 < policy.failure_threshold => {
+                Circuit::Closed {
+                    failures: failures + 1,
+                }
+            }
+            Circuit::Closed { .. } | Circuit::HalfOpen => open,
+            // Another request opened the circuit meanwhile.
+            Circuit::Open { until } => Circuit::Open { until },
+        };
+    }
+}
+
+impl<R: RestService, C: Clock> DataSourceBackend for RetryingRestService<R, C> {
+    fn query(&self, key: &str) -> Result<Vec<Data>, QueryError> {
+        self.send(|| self.rest_api.query(key))
+    }
+
+    fn query_with_timeout(&self, key: &str, timeout: Duration) -> Result<Vec<Data>, QueryError> {
+        self.send(|| self.rest_api.query_with_timeout(key, timeout))
+    }
+}
+
+impl<R: RestService, C: Clock> RestService for RetryingRestService<R, C> {}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::enum_bool::{
+        clock::MockClock,
+        good::DATA_KEY,
+        http::{Fixture, HttpRestService, StandInServer},
+    };
+
+    fn server(sequence: Vec<Fixture>) -> StandInServer {
+        StandInServer::start_sequences(HashMap::from([(DATA_KEY.to_owned(), sequence)])).unwrap()
+    }
+
+    fn service(
+        server: &StandInServer,
+        retry: RetryPolicy,
+        clock: &MockClock,
+    ) -> RetryingRestService<HttpRestService, MockClock> {
+        let rest_api = HttpRestService::new(server.addr()).with_timeout(Duration::from_millis(200));
+        RetryingRestService::with_clock(rest_api, retry.with_jitter(0.0), clock.clone())
+    }
+
+    fn data() -> Vec<Data> {
+        vec![Data::new("sensor-1", "21.5")]
+    }
+
+    #[test]
+    fn exponential_backoff() {
+        let policy =
+            RetryPolicy::default().with_backoff(Duration::from_millis(100), Duration::from_secs(1));
+        let delays: Vec<_> = (0..6)
+            .map(|retry| policy.backoff(retry).as_millis())
+            .collect();
+        assert_eq!(delays, [100, 200, 400, 800, 1000, 1000]);
+        assert_eq!(policy.backoff(u32::MAX), Duration::from_secs(1));
+    }
+
+    #[test]
+    fn jitter() {
+        let policy = RetryPolicy::default().with_jitter(0.5);
+        let backoff = policy.backoff(2);
+        for _ in 0..100 {
+            let delay = policy.delay(2);
+            assert!(delay <= backoff && delay >= backoff / 2, "{delay:?}");
+        }
+
+        let policy = policy.with_jitter(0.0);
+        assert_eq!(policy.delay(2), backoff);
+
+        for jitter in [1.5, -1.0, f64::INFINITY, f64::NAN] {
+            let policy = RetryPolicy { jitter, ..policy };
+            assert!(policy.delay(2) <= backoff);
+        }
+    }
+
+    #[test]
+    fn retry_transient_errors() {
+        let server = server(vec![
+            Fixture::status(503),
+            Fixture::status(500),
+            Fixture::data(&data()),
+        ]);
+        let clock = MockClock::new();
+        let start = clock.now();
+        let service = service(&server, RetryPolicy::default(), &clock);
+
+        assert_eq!(service.query(DATA_KEY), Ok(data()));
+        assert_eq!(server.request_count(DATA_KEY), 3);
+        assert_eq!(clock.now() - start, Duration::from_millis(100 + 200));
+    }
+
+    #[test]
+    fn give_up_after_max_attempts() {
+        let server = server(vec![Fixture::status(503)]);
+        let clock = MockClock::new();
+        let service = service(&server, RetryPolicy::default().with_max_attempts(4), &clock);
+
+        assert_eq!(service.query(DATA_KEY), Err(QueryError::Http(503)));
+        assert_eq!(server.request_count(DATA_KEY), 4);
+    }
+
+    #[test]
+    fn no_retry_on_permanent_errors() {
+        let server = server(vec![Fixture::status(404), Fixture::data(&data())]);
+        let clock = MockClock::new();
+        let service = service(&server, RetryPolicy::default(), &clock);
+
+        assert_eq!(
+            service.query(DATA_KEY),
+            Err(QueryError::NotFound(DATA_KEY.into()))
+        );
+        assert_eq!(server.request_count(DATA_KEY), 1);
+    }
+
+    #[test]
+    fn custom_retry_on() {
+        let server = server(vec![Fixture::json("not json"), Fixture::data(&data())]);
+        let clock = MockClock::new();
+        let retry = RetryPolicy::default()
+            .with_retry_on(|err| matches!(err, QueryError::InvalidResponse(_)));
+        let service = service(&server, retry, &clock);
+
+        assert_eq!(service.query(DATA_KEY), Ok(data()));
+        assert_eq!(server.request_count(DATA_KEY), 2);
+    }
+
+    #[test]
+    fn retry_timeouts() {
+        let server = server(vec![
+            Fixture::data(&data()).with_delay(Duration::from_secs(1)),
+            Fixture::data(&data()),
+        ]);
+        let clock = MockClock::new();
+        let service = service(&server, RetryPolicy::default(), &clock);
+
+        assert_eq!(service.query(DATA_KEY), Ok(data()));
+        assert_eq!(server.request_count(DATA_KEY), 2);
+    }
+
+    #[test]
+    fn circuit_breaker() {
+        let server = server(vec![
+            Fixture::status(503),
+            Fixture::status(503),
+            Fixture::status(503),
+            Fixture::data(&data()),
+        ]);
+        let clock = MockClock::new();
+        let cooldown = Duration::from_secs(10);
+        let breaker = CircuitBreakerPolicy {
+            failure_threshold: 2,
+            cooldown,
+        };
+        let service = service(&server, RetryPolicy::default().with_max_attempts(1), &clock)
+            .with_circuit_breaker(breaker);
+
+        assert_eq!(service.query(DATA_KEY), Err(QueryError::Http(503)));
+        assert_eq!(service.circuit_state(), CircuitState::Closed);
+        assert_eq!(service.query(DATA_KEY), Err(QueryError::Http(503)));
+        assert_eq!(service.circuit_state(), CircuitState::Open);
+        assert_eq!(service.query(DATA_KEY), Err(QueryError::CircuitOpen));
+        assert_eq!(server.request_count(DATA_KEY), 2);
+
+        // Failed trial request opens the circuit again.
+        clock.advance(cooldown);
+        assert_eq!(service.circuit_state(), CircuitState::HalfOpen);
+        assert_eq!(service.query(DATA_KEY), Err(QueryError::Http(503)));
+        assert_eq!(service.circuit_state(), CircuitState::Open);
+        assert_eq!(service.query(DATA_KEY), Err(QueryError::CircuitOpen));
+        assert_eq!(server.request_count(DATA_KEY), 3);
+
+        clock.advance(cooldown);
+        assert_eq!(service.query(DATA_KEY), Ok(data()));
+        assert_eq!(service.circuit_state(), CircuitState::Closed);
+        assert_eq!(server.request_count(DATA_KEY), 4);
+    }
+
+    #[test]
+    fn circuit_opens_within_retries() {
+        let server = server(vec![Fixture::status(500)]);
+        let clock = MockClock::new();
+        let breaker = CircuitBreakerPolicy {
+            failure_threshold: 2,
+            cooldown: Duration::from_secs(10),
+        };
+        let service = service(&server, RetryPolicy::default().with_max_attempts(5), &clock)
+            .with_circuit_breaker(breaker);
+
+        assert_eq!(service.query(DATA_KEY), Err(QueryError::Http(500)));
+        assert_eq!(server.request_count(DATA_KEY), 2);
+    }
+
+    #[test]
+    fn endless_cooldown() {
+        let server = server(vec![Fixture::status(503)]);
+        let clock = MockClock::new();
+        let breaker = CircuitBreakerPolicy {
+            failure_threshold: 1,
+            cooldown: Duration::MAX,
+        };
+        let service = service(&server, RetryPolicy::default().with_max_attempts(1), &clock)
+            .with_circuit_breaker(breaker);
+
+        assert_eq!(service.query(DATA_KEY), Err(QueryError::Http(503)));
+        clock.advance(Duration::from_secs(365 * 24 * 3600));
+        assert_eq!(service.circuit_state(), CircuitState::Open);
+        assert_eq!(service.query(DATA_KEY), Err(QueryError::CircuitOpen));
+        assert_eq!(server.request_count(DATA_KEY), 1);
+    }
+}
@@ -43,39 +43,348 @@ mod bad {
     //
 }
 
-mod good {
+pub mod good {
+    use std::fmt;
+
     use super::*;
 
-    struct CliArgs {
-        input: PathBuf,
-        interval: u64,
-        separtor: String,
-        custom_name: Option<String>,
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct CliArgs {
+        pub input: PathBuf,
+        pub interval: u64,
+        pub separtor: String,
+        pub custom_name: Option<String>,
     }
 
     impl CliArgs {
-        fn validate(&self) -> Result<(), &'static str> {
+        /// Checks all fields, reporting every problem at once.
+        pub fn validate(&self) -> Result<(), ValidationReport> {
             // We will get compiler error her on new types to remind
             // us to consider the new field
             let Self {
                 input,
                 interval,
-                separtor: _,
-                custom_name: _,
+                separtor,
+                custom_name,
             } = self;
 
+            let mut report = ValidationReport::default();
             if !input.exists() {
-                return Err("Input doesn't exist");
+                report.push(ValidationError::InputMissing(input.clone()));
             }
 
             if *interval == 0 {
-                return Err("Interval must be positive");
+                report.push(ValidationError::IntervalZero);
             }
 
-            return Ok(());
+            if separtor.is_empty() {
+                report.push(ValidationError::SeparatorEmpty);
+            }
+
+            if custom_name
+                .as_ref()
+                .is_some_and(|name| name.trim().is_empty())
+            {
+                report.push(ValidationError::CustomNameBlank);
+            }
+
+            report.into_result()
+        }
+
+        /// Parses the arguments following the program name and validates
+        /// them.
+        pub fn parse<I>(args: I) -> Result<Self, ArgsError>
+        where
+            I: IntoIterator,
+            I::Item: Into<String>,
+        {
+            let mut input = None;
+            let mut interval = None;
+            let mut separtor = None;
+            let mut custom_name = None;
+
+            let mut args = args.into_iter().map(Into::into);
+            while let Some(arg) = args.next() {
+                let (name, inline_value) = match arg.split_once('=') {
+                    Some((name, value)) => (name.to_owned(), Some(value.to_owned())),
+                    None => (arg, None),
+                };
+                let option = CliOption::ALL
+                    .into_iter()
+                    .find(|option| name.strip_prefix("--") == Some(option.name()))
+                    .ok_or_else(|| ArgsError::UnknownArgument(name.clone()))?;
+
+                let Some(value_name) = option.value_name() else {
+                    if inline_value.is_some() {
+                        return Err(ArgsError::UnexpectedValue(option.name()));
+                    }
+                    return Err(ArgsError::HelpRequested);
+                };
+                let value =
+                    inline_value
+                        .or_else(|| args.next())
+                        .ok_or(ArgsError::MissingValue {
+                            option: option.name(),
+                            value: value_name,
+                        })?;
+
+                match option {
+                    CliOption::Input => set(&mut input, option, PathBuf::from(value))?,
+                    CliOption::Interval => {
+                        let seconds = value.parse().map_err(|_| ArgsError::InvalidValue {
+                            option: option.name(),
+                            value: value.clone(),
+                        })?;
+                        set(&mut interval, option, seconds)?
+                    }
+                    CliOption::Separator => set(&mut separtor, option, value)?,
+                    CliOption::CustomName => set(&mut custom_name, option, value)?,
+                    CliOption::Help => return Err(ArgsError::HelpRequested),
+                }
+            }
+
+            // Building the struct fails to compile when a field is added,
+            // just like destructuring it in `validate`.
+            let args = Self {
+                input: input.ok_or(ArgsError::MissingOption(CliOption::Input.name()))?,
+                interval: interval.unwrap_or(DEFAULT_INTERVAL),
+                separtor: separtor.unwrap_or_else(|| DEFAULT_SEPARATOR.to_owned()),
+                custom_name,
+            };
+            args.validate().map_err(ArgsError::Invalid)?;
+            Ok(args)
+        }
+
+        /// Parses the arguments of the running program.
+        pub fn from_env() -> Result<Self, ArgsError> {
+            Self::parse(std::env::args().skip(1))
+        }
+
+        pub fn usage() -> String {
+            let mut usage = format!("Usage: {PROGRAM}");
+            for option in CliOption::ALL {
+                let Some(value) = option.value_name() else {
+                    continue;
+                };
+                let arg = format!("--{} <{value}>", option.name());
+                if option.required() {
+                    usage.push_str(&format!(" {arg}"));
+                } else {
+                    usage.push_str(&format!(" [{arg}]"));
+                }
+            }
+            usage
+        }
+
+        pub fn help() -> String {
+            let mut help = format!("{}\n\nOptions:\n", Self::usage());
+            for option in CliOption::ALL {
+                let arg = match option.value_name() {
+                    Some(value) => format!("--{} <{value}>", option.name()),
+                    None => format!("--{}", option.name()),
+                };
+                help.push_str(&format!("  {arg:<24}{}\n", option.help()));
+            }
+            help
+        }
+    }
+
+    const PROGRAM: &str = "present";
+    const DEFAULT_INTERVAL: u64 = 60;
+    const DEFAULT_SEPARATOR: &str = ",";
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    enum CliOption {
+        Input,
+        Interval,
+        Separator,
+        CustomName,
+        Help,
+    }
+
+    impl CliOption {
+        /// Options in the order of the help.
+        const ALL: [CliOption; 5] = [
+            CliOption::Input,
+            CliOption::Interval,
+            CliOption::Separator,
+            CliOption::CustomName,
+            CliOption::Help,
+        ];
+
+        fn name(self) -> &'static str {
+            match self {
+                CliOption::Input => "input",
+                CliOption::Interval => "interval",
+                CliOption::Separator => "separator",
+                CliOption::CustomName => "custom-name",
+                CliOption::Help => "help",
+            }
+        }
+
+        /// Name of the value, or `None` for flags.
+        fn value_name(self) -> Option<&'static str> {
+            match self {
+                CliOption::Input => Some("PATH"),
+                CliOption::Interval => Some("SECONDS"),
+                CliOption::Separator => Some("TEXT"),
+                CliOption::CustomName => Some("NAME"),
+                CliOption::Help => None,
+            }
+        }
+
+        fn required(self) -> bool {
+            matches!(self, CliOption::Input)
+        }
+
+        fn help(self) -> &'static str {
+            match self {
+                CliOption::Input => "File to read the data from",
+                CliOption::Interval => "Seconds between reads [default: 60]",
+                CliOption::Separator => "Separator of the values [default: ,]",
+                CliOption::CustomName => "Name to show instead of the input",
+                CliOption::Help => "Print this help",
+            }
+        }
+    }
+
+    fn set<T>(field: &mut Option<T>, option: CliOption, value: T) -> Result<(), ArgsError> {
+        if field.replace(value).is_some() {
+            return Err(ArgsError::DuplicateOption(option.name()));
+        }
+        Ok(())
+    }
+
+    /// Problem with one field of [`CliArgs`].
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub enum ValidationError {
+        InputMissing(PathBuf),
+        IntervalZero,
+        SeparatorEmpty,
+        /// Custom name is given but has no visible characters.
+        CustomNameBlank,
+    }
+
+    impl fmt::Display for ValidationError {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match self {
+                ValidationError::InputMissing(path) => {
+                    write!(f, "Input '{}' doesn't exist", path.display())
+                }
+                ValidationError::IntervalZero => f.write_str("Interval must be positive"),
+                ValidationError::SeparatorEmpty => f.write_str("Separator must not be empty"),
+                ValidationError::CustomNameBlank => f.write_str("Custom name must not be blank"),
+            }
+        }
+    }
+
+    impl std::error::Error for ValidationError {}
+
+    /// All problems found while validating [`CliArgs`].
+    #[derive(Debug, Clone, Default, PartialEq, Eq)]
+    pub struct ValidationReport {
+        errors: Vec<ValidationError>,
+    }
+
+    impl ValidationReport {
+        pub fn push(&mut self, error: ValidationError) {
+            self.errors.push(error);
+        }
+
+        pub fn errors(&self) -> &[ValidationError] {
+            &self.errors
+        }
+
+        pub fn is_empty(&self) -> bool {
+            self.errors.is_empty()
+        }
+
+        /// Fails with the report if any problem was found.
+        pub fn into_result(self) -> Result<(), ValidationReport> {
+            if self.is_empty() {
+                Ok(())
+            } else {
+                Err(self)
+            }
         }
     }
 
+    /// Lists each problem on its own line.
+    impl fmt::Display for ValidationReport {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match self.errors.len() {
+                1 => f.write_str("Invalid argument:")?,
+                _ => write!(f, "{} invalid arguments:", self.errors.len())?,
+            }
+            for error in &self.errors {
+                write!(f, "\n  - {error}")?;
+            }
+            Ok(())
+        }
+    }
+
+    impl std::error::Error for ValidationReport {}
+
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub enum ArgsError {
+        /// `--help` was given, so the help should be printed instead.
+        HelpRequested,
+        UnknownArgument(String),
+        MissingOption(&'static str),
+        MissingValue {
+            option: &'static str,
+            value: &'static str,
+        },
+        /// Value was given to a flag.
+        UnexpectedValue(&'static str),
+        DuplicateOption(&'static str),
+        InvalidValue {
+            option: &'static str,
+            value: String,
+        },
+        /// Arguments were parsed but failed validation.
+        Invalid(ValidationReport),
+    }
+
+    impl ArgsError {
+        /// Text to print for the error: the help if requested, otherwise the
+        /// error with the usage.
+        pub fn report(&self) -> String {
+            match self {
+                ArgsError::HelpRequested => CliArgs::help(),
+                err => format!(
+                    "error: {err}\n\n{}\n\nFor more information, try '--help'.\n",
+                    CliArgs::usage()
+                ),
+            }
+        }
+    }
+
+    impl fmt::Display for ArgsError {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match self {
+                ArgsError::HelpRequested => f.write_str("Help requested"),
+                ArgsError::UnknownArgument(arg) => write!(f, "Unexpected argument '{arg}'"),
+                ArgsError::MissingOption(name) => write!(f, "Option '--{name}' is required"),
+                ArgsError::MissingValue { option, value } => {
+                    write!(f, "Option '--{option}' needs a value <{value}>")
+                }
+                ArgsError::UnexpectedValue(name) => {
+                    write!(f, "Flag '--{name}' doesn't take a value")
+                }
+                ArgsError::DuplicateOption(name) => {
+                    write!(f, "Option '--{name}' is given more than once")
+                }
+                ArgsError::InvalidValue { option, value } => {
+                    write!(f, "Invalid value '{value}' for '--{option}'")
+                }
+                ArgsError::Invalid(report) => write!(f, "{report}"),
+            }
+        }
+    }
+
+    impl std::error::Error for ArgsError {}
+
     struct Person {
         first_name: String,
         last_name: String,
@@ -98,7 +407,159 @@ mod good {
         }
     }
 
-    //
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        const INPUT: &str = env!("CARGO_MANIFEST_DIR");
+
+        fn parse(args: &[&str]) -> Result<CliArgs, ArgsError> {
+            CliArgs::parse(args.iter().copied())
+        }
+
+        #[test]
+        fn parse_args() {
+            let args = parse(&[
+                "--input",
+                INPUT,
+                "--interval=5",
+                "--separator",
+                ";",
+                "--custom-name",
+                "sensors",
+            ])
+            .unwrap();
+            assert_eq!(args.input, PathBuf::from(INPUT));
+            assert_eq!(args.interval, 5);
+            assert_eq!(args.separtor, ";");
+            assert_eq!(args.custom_name.as_deref(), Some("sensors"));
+
+            let args = parse(&["--input", INPUT]).unwrap();
+            assert_eq!(args.interval, DEFAULT_INTERVAL);
+            assert_eq!(args.separtor, DEFAULT_SEPARATOR);
+            assert_eq!(args.custom_name, None);
+        }
+
+        #[test]
+        fn usage_errors() {
+            let cases: [(&[&str], ArgsError); 7] = [
+                (&[], ArgsError::MissingOption("input")),
+                (&["input"], ArgsError::UnknownArgument("input".into())),
+                (
+                    &["--verbose"],
+                    ArgsError::UnknownArgument("--verbose".into()),
+                ),
+                (
+                    &["--input"],
+                    ArgsError::MissingValue {
+                        option: "input",
+                        value: "PATH",
+                    },
+                ),
+                (
+                    &["--input", INPUT, "--interval", "soon"],
+                    ArgsError::InvalidValue {
+                        option: "interval",
+                        value: "soon".into(),
+                    },
+                ),
+                (
+                    &["--input", INPUT, "--input", INPUT],
+                    ArgsError::DuplicateOption("input"),
+                ),
+                (&["--help=yes"], ArgsError::UnexpectedValue("help")),
+            ];
+            for (args, err) in cases {
+                assert_eq!(parse(args).unwrap_err(), err, "{args:?}");
+            }
+        }
+
+        #[test]
+        fn validate_parsed() {
+            let err = parse(&["--input", INPUT, "--interval", "0", "--separator="]).unwrap_err();
+            let ArgsError::Invalid(report) = &err else {
+                panic!("Arguments must fail validation");
+            };
+            assert_eq!(
+                report.errors(),
+                [
+                    ValidationError::IntervalZero,
+                    ValidationError::SeparatorEmpty
+                ]
+            );
+            assert!(err.report().starts_with(
+                "error: 2 invalid arguments:\n  \
+                 - Interval must be positive\n  \
+                 - Separator must not be empty\n"
+            ));
+        }
+
+        #[test]
+        fn help() {
+            let err = parse(&["--input", INPUT, "--help"]).unwrap_err();
+            assert_eq!(err, ArgsError::HelpRequested);
+            assert_eq!(
+                err.report(),
+                "Usage: present --input <PATH> [--interval <SECONDS>] [--separator <TEXT>] \
+                 [--custom-name <NAME>]\n\
+                 \n\
+                 Options:\n  \
+                 --input <PATH>          File to read the data from\n  \
+                 --interval <SECONDS>    Seconds between reads [default: 60]\n  \
+                 --separator <TEXT>      Separator of the values [default: ,]\n  \
+                 --custom-name <NAME>    Name to show instead of the input\n  \
+                 --help                  Print this help\n"
+            );
+
+            let report = parse(&["--interval"]).unwrap_err().report();
+            assert!(report.starts_with("error: Option '--interval' needs a value <SECONDS>\n"));
+            assert!(report.contains(&CliArgs::usage()));
+        }
+
+        #[test]
+        fn accumulate_validation_errors() {
+            let args = CliArgs {
+                input: PathBuf::from("/does/not/exist"),
+                interval: 0,
+                separtor: String::new(),
+                custom_name: Some("  ".into()),
+            };
+            let report = args.validate().unwrap_err();
+            assert_eq!(
+                report.errors(),
+                [
+                    ValidationError::InputMissing(PathBuf::from("/does/not/exist")),
+                    ValidationError::IntervalZero,
+                    ValidationError::SeparatorEmpty,
+                    ValidationError::CustomNameBlank,
+                ]
+            );
+            assert_eq!(
+                report.to_string(),
+                "4 invalid arguments:\n  \
+                 - Input '/does/not/exist' doesn't exist\n  \
+                 - Interval must be positive\n  \
+                 - Separator must not be empty\n  \
+                 - Custom name must not be blank"
+            );
+
+            let args = CliArgs {
+                interval: 1,
+                separtor: ";".into(),
+                custom_name: None,
+                ..args
+            };
+            assert_eq!(
+                args.validate().unwrap_err().to_string(),
+                "Invalid argument:\n  - Input '/does/not/exist' doesn't exist"
+            );
+            let args = CliArgs {
+                input: PathBuf::from(INPUT),
+                ..args
+            };
+            assert_eq!(args.validate(), Ok(()));
+        }
+    }
 }
 
 mod enumss {
@@ -184,33 +645,3 @@ mod pitfalls {
 
     //
 }
-
-mod enumss {
-
-    #[derive(Debug, Clone, Copy, Eq, PartialEq)]
-    enum Kinds {
-        First,
-        Second,
-        Third,
-    }
-
-    impl Kinds {
-        fn all() -> &'static [Kinds] {
-            // Reminder to add new kinds below
-            match Kinds::First {
-                Kinds::First => {}
-                Kinds::Second => {}
-                Kinds::Third => {}
-            }
-
-            &[Kinds::First, Kinds::Second, Kinds::Third]
-        }
-    }
-
-    #[test]
-    fn test_all() {
-        let all = Kinds::all();
-        // This will pass if we forget to extend all function with the new item
-        assert_eq!(all, &[Kinds::First, Kinds::Second, Kinds::Third])
-    }
-}
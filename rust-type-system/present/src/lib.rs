@@ -0,0 +1,6 @@
+pub mod enum_bool;
+pub mod exhaustive_pattern;
+// mod invariant;
+pub mod new_type;
+mod proptest;
+pub mod state_pattern;
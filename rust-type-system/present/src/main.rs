@@ -1,10 +1,3 @@
-// mod enum_bool;
-// mod exhaustive_pattern;
-// mod invariant;
-mod new_type;
-mod proptest;
-// mod state_pattern;
-
 fn main() {
     println!("Hello, world!");
 }
@@ -1,3 +1,10 @@
+pub mod csv;
+pub mod geo;
+pub mod id;
+mod macros;
+pub mod storage;
+pub mod temperature;
+
 // Bad:
 
 mod bad {
@@ -43,59 +50,357 @@ mod change {
 
 // Good:
 
-mod good {
+pub mod good {
+    use std::{
+        fmt,
+        ops::{Add, Div, Mul, Sub},
+    };
 
-    struct Meter(f32);
-    struct Second(f32);
+    #[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+    pub struct Meter(pub(super) f32);
+    #[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+    pub struct Second(f32);
 
-    struct Speed {
+    pub struct Speed {
         distance: Meter,
         time: Second,
     }
 
     impl Speed {
-        fn new(distance: Meter, time: Second) -> Self {
-            Self { distance, time }
+        /// Creates a speed, refusing a zero time which would
+        /// lead to infinite speed.
+        pub fn new(distance: Meter, time: Second) -> Result<Self, QuantityError> {
+            if time.0 == 0.0 {
+                return Err(QuantityError::ZeroTime);
+            }
+
+            Ok(Self { distance, time })
         }
 
-        fn meter_per_second(&self) -> f32 {
+        pub fn meter_per_second(&self) -> f32 {
             self.distance.0 / self.time.0
         }
 
-        fn kilometer_per_hour(&self) -> f32 {
+        pub fn kilometer_per_hour(&self) -> f32 {
             (self.distance.0 / 1000.0) / (self.time.0 / 3600.0)
         }
     }
 
-    fn uses() {
+    fn uses() -> Result<(), QuantityError> {
         let distance = Meter(10.0);
         let time = Second(20.0);
-        let meter_per_second = Speed::new(distance, time).meter_per_second();
+        let meter_per_second = Speed::new(distance, time)?.meter_per_second();
+        Ok(())
+    }
+
+    // Arithmetic:
+
+    /// Derived dimension from dividing [`Meter`] by [`Second`].
+    #[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+    pub struct MetersPerSecond(f32);
+
+    impl Meter {
+        pub fn value(&self) -> f32 {
+            self.0
+        }
+    }
+
+    impl Second {
+        pub fn value(&self) -> f32 {
+            self.0
+        }
+    }
+
+    impl MetersPerSecond {
+        pub fn value(&self) -> f32 {
+            self.0
+        }
+    }
+
+    /// Implements `Add`, `Sub` and `Div` between quantities of the same dimension.
+    ///
+    /// The operators don't validate their result, like `f32` itself. Use the
+    /// `checked_*` methods to reject negative, infinite or NaN results.
+    macro_rules! same_dimension_ops {
+        ($($quantity:ident),*) => {$(
+            impl Add for $quantity {
+                type Output = $quantity;
+
+                fn add(self, rhs: $quantity) -> Self::Output {
+                    $quantity(self.0 + rhs.0)
+                }
+            }
+
+            impl Sub for $quantity {
+                type Output = $quantity;
+
+                fn sub(self, rhs: $quantity) -> Self::Output {
+                    $quantity(self.0 - rhs.0)
+                }
+            }
+
+            /// Dividing two quantities of the same dimension gives a dimensionless scalar.
+            impl Div for $quantity {
+                type Output = f32;
+
+                fn div(self, rhs: $quantity) -> Self::Output {
+                    self.0 / rhs.0
+                }
+            }
+
+            impl $quantity {
+                pub fn checked_add(self, rhs: $quantity) -> Result<$quantity, QuantityError> {
+                    QuantityError::check((self + rhs).0).map($quantity)
+                }
+
+                pub fn checked_sub(self, rhs: $quantity) -> Result<$quantity, QuantityError> {
+                    QuantityError::check((self - rhs).0).map($quantity)
+                }
+
+                pub fn checked_div(self, rhs: $quantity) -> Result<f32, QuantityError> {
+                    if rhs.0 == 0.0 {
+                        return Err(QuantityError::DivisionByZero);
+                    }
+
+                    QuantityError::check(self / rhs)
+                }
+            }
+        )*};
+    }
+
+    same_dimension_ops!(Meter, Second, MetersPerSecond);
+
+    impl Div<Second> for Meter {
+        type Output = MetersPerSecond;
+
+        fn div(self, rhs: Second) -> Self::Output {
+            MetersPerSecond(self.0 / rhs.0)
+        }
+    }
+
+    impl Div<MetersPerSecond> for Meter {
+        type Output = Second;
+
+        fn div(self, rhs: MetersPerSecond) -> Self::Output {
+            Second(self.0 / rhs.0)
+        }
+    }
+
+    impl Mul<Second> for MetersPerSecond {
+        type Output = Meter;
+
+        fn mul(self, rhs: Second) -> Self::Output {
+            Meter(self.0 * rhs.0)
+        }
+    }
+
+    impl Mul<MetersPerSecond> for Second {
+        type Output = Meter;
+
+        fn mul(self, rhs: MetersPerSecond) -> Self::Output {
+            Meter(self.0 * rhs.0)
+        }
+    }
+
+    impl Meter {
+        /// Checked `self / time`, refusing a zero time.
+        pub fn checked_div_time(self, time: Second) -> Result<MetersPerSecond, QuantityError> {
+            if time.0 == 0.0 {
+                return Err(QuantityError::ZeroTime);
+            }
+
+            QuantityError::check((self / time).0).map(MetersPerSecond)
+        }
+
+        /// Checked `self / speed`, refusing a zero speed.
+        pub fn checked_div_speed(self, speed: MetersPerSecond) -> Result<Second, QuantityError> {
+            if speed.0 == 0.0 {
+                return Err(QuantityError::DivisionByZero);
+            }
+
+            QuantityError::check((self / speed).0).map(Second)
+        }
+    }
+
+    impl MetersPerSecond {
+        /// Checked `self * time`, refusing a distance overflowing to infinity.
+        pub fn checked_mul(self, time: Second) -> Result<Meter, QuantityError> {
+            QuantityError::check((self * time).0).map(Meter)
+        }
     }
 
     // Pitfalls:
 
-    impl From<f32> for Meter {
-        fn from(value: f32) -> Self {
-            Self(value)
+    // Implementing `From<f32>` would accept any number, including NaN, infinities
+    // and negative values. Validated conversions reject them instead.
+
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub enum QuantityError {
+        NotFinite(f32),
+        Negative(f32),
+        /// Speed with zero time would be infinite.
+        ZeroTime,
+        /// Dividing by a zero quantity has no finite result.
+        DivisionByZero,
+    }
+
+    impl fmt::Display for QuantityError {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match self {
+                QuantityError::NotFinite(value) => {
+                    write!(f, "Quantity must be finite, got {value}")
+                }
+                QuantityError::Negative(value) => {
+                    write!(f, "Quantity can't be negative, got {value}")
+                }
+                QuantityError::ZeroTime => f.write_str("Time of speed can't be zero"),
+                QuantityError::DivisionByZero => f.write_str("Can't divide by a zero quantity"),
+            }
         }
     }
 
-    impl From<f32> for Second {
-        fn from(value: f32) -> Self {
-            Self(value)
+    impl std::error::Error for QuantityError {}
+
+    impl QuantityError {
+        /// Ensures the value is finite and not negative, which includes `-0.0`.
+        pub(super) fn check(value: f32) -> Result<f32, QuantityError> {
+            if !value.is_finite() {
+                return Err(QuantityError::NotFinite(value));
+            }
+
+            if value.is_sign_negative() {
+                return Err(QuantityError::Negative(value));
+            }
+
+            Ok(value)
         }
     }
 
-    fn use_pitfall() {
+    impl TryFrom<f32> for Meter {
+        type Error = QuantityError;
+
+        fn try_from(value: f32) -> Result<Self, Self::Error> {
+            QuantityError::check(value).map(Self)
+        }
+    }
+
+    impl TryFrom<f32> for Second {
+        type Error = QuantityError;
+
+        fn try_from(value: f32) -> Result<Self, Self::Error> {
+            QuantityError::check(value).map(Self)
+        }
+    }
+
+    fn use_pitfall() -> Result<(), QuantityError> {
         let distance_meter = 10.0;
         let time_seconds = 20.0;
+        // Still no compiler error on swapped units, but invalid values are rejected.
         let meter_per_second =
-            Speed::new(distance_meter.into(), time_seconds.into()).meter_per_second();
+            Speed::new(distance_meter.try_into()?, time_seconds.try_into()?)?.meter_per_second();
+        Ok(())
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn same_dimension() {
+            assert_eq!(Meter(10.0) + Meter(5.0) + Meter(1.0), Meter(16.0));
+            assert_eq!(Meter(10.0) - Meter(5.0), Meter(5.0));
+            assert_eq!(Second(3.0) + Second(2.0), Second(5.0));
+            assert_eq!(
+                MetersPerSecond(1.5) - MetersPerSecond(0.5),
+                MetersPerSecond(1.0)
+            );
+
+            // Compiler error. Yay!
+            // let mixed = Meter(10.0) + Second(20.0);
+        }
+
+        #[test]
+        fn checked_arithmetic() {
+            assert_eq!(Meter(10.0).checked_add(Meter(5.0)), Ok(Meter(15.0)));
+            assert_eq!(
+                Second(2.0).checked_sub(Second(3.0)),
+                Err(QuantityError::Negative(-1.0))
+            );
+            assert_eq!(
+                Meter(f32::MAX).checked_add(Meter(f32::MAX)),
+                Err(QuantityError::NotFinite(f32::INFINITY))
+            );
+            assert_eq!(
+                Meter(1.0).checked_div(Meter(0.0)),
+                Err(QuantityError::DivisionByZero)
+            );
+            assert_eq!(
+                Meter(100.0).checked_div_time(Second(20.0)),
+                Ok(MetersPerSecond(5.0))
+            );
+            assert_eq!(
+                Meter(1.0).checked_div_time(Second(0.0)),
+                Err(QuantityError::ZeroTime)
+            );
+            assert_eq!(
+                Meter(1.0).checked_div_speed(MetersPerSecond(0.0)),
+                Err(QuantityError::DivisionByZero)
+            );
+            assert_eq!(
+                MetersPerSecond(f32::MAX).checked_mul(Second(2.0)),
+                Err(QuantityError::NotFinite(f32::INFINITY))
+            );
+        }
+
+        #[test]
+        fn validated_construction() {
+            assert_eq!(Meter::try_from(10.0), Ok(Meter(10.0)));
+            assert_eq!(Second::try_from(0.0), Ok(Second(0.0)));
+            assert_eq!(Meter::try_from(-1.0), Err(QuantityError::Negative(-1.0)));
+            assert!(matches!(
+                Second::try_from(-0.0),
+                Err(QuantityError::Negative(_))
+            ));
+            assert_eq!(
+                Second::try_from(f32::INFINITY),
+                Err(QuantityError::NotFinite(f32::INFINITY))
+            );
+            assert!(matches!(
+                Meter::try_from(f32::NAN),
+                Err(QuantityError::NotFinite(_))
+            ));
+        }
+
+        #[test]
+        fn speed_refuses_zero_time() {
+            assert!(matches!(
+                Speed::new(Meter(10.0), Second(0.0)),
+                Err(QuantityError::ZeroTime)
+            ));
+
+            let speed = Speed::new(Meter(10.0), Second(2.0)).unwrap();
+            assert_eq!(speed.meter_per_second(), 5.0);
+        }
+
+        #[test]
+        fn derived_dimensions() {
+            let speed = Meter(100.0) / Second(20.0);
+            assert_eq!(speed, MetersPerSecond(5.0));
+            assert_eq!(speed * Second(4.0), Meter(20.0));
+            assert_eq!(Second(4.0) * speed, Meter(20.0));
+            assert_eq!(Meter(20.0) / speed, Second(4.0));
+        }
+
+        #[test]
+        fn dimensionless_ratio() {
+            let ratio = Meter(30.0) / Meter(10.0);
+            assert_eq!(ratio, 3.0);
+        }
     }
 }
 
 mod pitfall {
+    use super::good::QuantityError;
 
     struct Kilometer(f32);
     struct Hour(f32);
@@ -104,48 +409,361 @@ mod pitfall {
         time: Hour,
     }
 
-    impl From<f32> for Kilometer {
-        fn from(value: f32) -> Self {
-            Self(value)
+    impl TryFrom<f32> for Kilometer {
+        type Error = QuantityError;
+
+        fn try_from(value: f32) -> Result<Self, Self::Error> {
+            QuantityError::check(value).map(Self)
         }
     }
 
-    impl From<f32> for Hour {
-        fn from(value: f32) -> Self {
-            Self(value)
+    impl TryFrom<f32> for Hour {
+        type Error = QuantityError;
+
+        fn try_from(value: f32) -> Result<Self, Self::Error> {
+            QuantityError::check(value).map(Self)
         }
     }
 
     impl Speed {
-        fn new(distance: Kilometer, time: Hour) -> Self {
-            Self { distance, time }
+        fn new(distance: Kilometer, time: Hour) -> Result<Self, QuantityError> {
+            if time.0 == 0.0 {
+                return Err(QuantityError::ZeroTime);
+            }
+
+            Ok(Self { distance, time })
         }
     }
 
-    fn use_pitfall() {
+    fn use_pitfall() -> Result<(), QuantityError> {
         let distance_meter = 10.0;
         let time_seconds = 20.0;
         // No compiler error.
-        let meter_per_second = Speed::new(distance_meter.into(), time_seconds.into());
+        let meter_per_second = Speed::new(distance_meter.try_into()?, time_seconds.try_into()?)?;
+        Ok(())
     }
 }
 
-mod generics {
+pub mod generics {
+    use std::{fmt, num::ParseFloatError, str::FromStr};
 
-    struct Kilometer(f32);
-    struct Meter(f32);
+    use super::good::QuantityError;
 
-    struct Hour(f32);
-    struct Second(f32);
+    /// Unit of length with its conversion factor to the base unit [`Meter`].
+    pub trait LengthUnit: Sized {
+        /// Count of meters in one unit.
+        const METERS: f32;
+        /// Symbol used when printing and parsing the unit.
+        const SYMBOL: &'static str;
+
+        fn new(value: f32) -> Self;
+
+        fn value(&self) -> f32;
+
+        fn to_meters(&self) -> f32 {
+            self.value() * Self::METERS
+        }
+
+        fn from_meters(meters: f32) -> Self {
+            Self::new(meters / Self::METERS)
+        }
+    }
+
+    /// Unit of time with its conversion factor to the base unit [`Second`].
+    pub trait TimeUnit: Sized {
+        /// Count of seconds in one unit.
+        const SECONDS: f32;
+        /// Symbol used when printing and parsing the unit.
+        const SYMBOL: &'static str;
+
+        fn new(value: f32) -> Self;
+
+        fn value(&self) -> f32;
+
+        fn to_seconds(&self) -> f32 {
+            self.value() * Self::SECONDS
+        }
+
+        fn from_seconds(seconds: f32) -> Self {
+            Self::new(seconds / Self::SECONDS)
+        }
+    }
+
+    crate::unit!(@base Meter: Length, symbol = "m");
+    crate::unit!(Kilometer: Length = 1000.0 * Meter, symbol = "km");
 
-    struct Speed<Dist, Time> {
+    crate::unit!(@base Second: Time, symbol = "s");
+    crate::unit!(Hour: Time = 3600.0 * Second, symbol = "h");
+
+    #[derive(Debug, Clone, Copy)]
+    pub struct Speed<Dist, Time> {
         distance: Dist,
         time: Time,
     }
 
     impl<Dist, Time> Speed<Dist, Time> {
-        fn new(distance: Dist, time: Time) -> Self {
-            Self { distance, time }
+        pub fn distance(&self) -> &Dist {
+            &self.distance
+        }
+
+        pub fn time(&self) -> &Time {
+            &self.time
+        }
+    }
+
+    impl<Dist: LengthUnit, Time: TimeUnit> Speed<Dist, Time> {
+        /// Creates a speed, refusing values which aren't finite or are
+        /// negative as well as a zero time which would lead to infinite speed.
+        /// The speed in its units must be finite too, e.g. a huge distance
+        /// in less than one time unit is refused.
+        pub fn new(distance: Dist, time: Time) -> Result<Self, QuantityError> {
+            QuantityError::check(distance.value())?;
+            if QuantityError::check(time.value())? == 0.0 {
+                return Err(QuantityError::ZeroTime);
+            }
+            QuantityError::check(distance.value() / time.value())?;
+
+            Ok(Self { distance, time })
+        }
+
+        pub fn meter_per_second(&self) -> f32 {
+            self.distance.to_meters() / self.time.to_seconds()
+        }
+
+        pub fn kilometer_per_hour(&self) -> f32 {
+            let kilometers = self.distance.to_meters() / Kilometer::METERS;
+            let hours = self.time.to_seconds() / Hour::SECONDS;
+            kilometers / hours
+        }
+
+        /// Converts the distance and time into the given units.
+        /// The speed itself stays the same, but fails if the values don't
+        /// fit into the new units.
+        pub fn convert<D: LengthUnit, T: TimeUnit>(&self) -> Result<Speed<D, T>, QuantityError> {
+            Speed::new(
+                D::from_meters(self.distance.to_meters()),
+                T::from_seconds(self.time.to_seconds()),
+            )
+        }
+    }
+
+    // Speeds are compared as meter per second regardless of their units.
+
+    impl<D1, T1, D2, T2> PartialEq<Speed<D2, T2>> for Speed<D1, T1>
+    where
+        D1: LengthUnit,
+        T1: TimeUnit,
+        D2: LengthUnit,
+        T2: TimeUnit,
+    {
+        fn eq(&self, other: &Speed<D2, T2>) -> bool {
+            self.meter_per_second() == other.meter_per_second()
+        }
+    }
+
+    impl<D1, T1, D2, T2> PartialOrd<Speed<D2, T2>> for Speed<D1, T1>
+    where
+        D1: LengthUnit,
+        T1: TimeUnit,
+        D2: LengthUnit,
+        T2: TimeUnit,
+    {
+        fn partial_cmp(&self, other: &Speed<D2, T2>) -> Option<std::cmp::Ordering> {
+            self.meter_per_second()
+                .partial_cmp(&other.meter_per_second())
+        }
+    }
+
+    // Text representation:
+
+    /// Units known while parsing, used to convert between units of the
+    /// same dimension.
+    const LENGTH_UNITS: &[(&str, f32)] = &[
+        (Meter::SYMBOL, Meter::METERS),
+        (Kilometer::SYMBOL, Kilometer::METERS),
+    ];
+    const TIME_UNITS: &[(&str, f32)] = &[
+        (Second::SYMBOL, Second::SECONDS),
+        (Hour::SYMBOL, Hour::SECONDS),
+    ];
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum Dimension {
+        Length,
+        Time,
+        Speed,
+    }
+
+    impl fmt::Display for Dimension {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match self {
+                Dimension::Length => f.write_str("length"),
+                Dimension::Time => f.write_str("time"),
+                Dimension::Speed => f.write_str("speed"),
+            }
+        }
+    }
+
+    #[derive(Debug, Clone, PartialEq)]
+    pub enum ParseQuantityError {
+        /// The text has a number without a unit.
+        MissingUnit,
+        InvalidNumber(ParseFloatError),
+        /// The number can't be a value of the quantity, e.g. a negative speed.
+        InvalidQuantity(QuantityError),
+        UnknownUnit(String),
+        /// The unit is known but measures another dimension.
+        WrongDimension {
+            expected: Dimension,
+            unit: String,
+        },
+    }
+
+    impl fmt::Display for ParseQuantityError {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match self {
+                ParseQuantityError::MissingUnit => f.write_str("Quantity has no unit"),
+                ParseQuantityError::InvalidNumber(err) => write!(f, "Invalid number: {err}"),
+                ParseQuantityError::InvalidQuantity(err) => write!(f, "Invalid quantity: {err}"),
+                ParseQuantityError::UnknownUnit(unit) => write!(f, "Unknown unit '{unit}'"),
+                ParseQuantityError::WrongDimension { expected, unit } => {
+                    write!(f, "Unit '{unit}' isn't a unit of {expected}")
+                }
+            }
+        }
+    }
+
+    impl std::error::Error for ParseQuantityError {}
+
+    /// Splits text like "10 km" or "1e3km" into its number and unit.
+    ///
+    /// Without whitespace, the unit starts after the last digit, since numbers
+    /// can contain letters as in "1e3".
+    fn split_quantity(text: &str) -> Result<(f32, &str), ParseQuantityError> {
+        let text = text.trim();
+        let split_at = text.find(char::is_whitespace).unwrap_or_else(|| {
+            text.rfind(|c: char| c.is_ascii_digit() || c == '.')
+                .map_or(0, |index| index + 1)
+        });
+
+        let (number, unit) = text.split_at(split_at);
+        let unit = unit.trim_start();
+        if unit.is_empty() {
+            return Err(ParseQuantityError::MissingUnit);
+        }
+
+        let number = number.parse().map_err(ParseQuantityError::InvalidNumber)?;
+
+        Ok((number, unit))
+    }
+
+    fn lookup(units: &[(&str, f32)], symbol: &str) -> Option<f32> {
+        units
+            .iter()
+            .find(|(unit, _)| *unit == symbol)
+            .map(|(_, factor)| *factor)
+    }
+
+    /// Resolves the factor of a length unit to meters.
+    fn length_factor(unit: &str, expected: Dimension) -> Result<f32, ParseQuantityError> {
+        if let Some(factor) = lookup(LENGTH_UNITS, unit) {
+            return Ok(factor);
+        }
+
+        let is_known = lookup(TIME_UNITS, unit).is_some() || unit.contains('/');
+        Err(unit_error(unit, expected, is_known))
+    }
+
+    /// Resolves the factor of a time unit to seconds.
+    fn time_factor(unit: &str, expected: Dimension) -> Result<f32, ParseQuantityError> {
+        if let Some(factor) = lookup(TIME_UNITS, unit) {
+            return Ok(factor);
+        }
+
+        let is_known = lookup(LENGTH_UNITS, unit).is_some() || unit.contains('/');
+        Err(unit_error(unit, expected, is_known))
+    }
+
+    fn unit_error(unit: &str, expected: Dimension, is_known: bool) -> ParseQuantityError {
+        if is_known {
+            ParseQuantityError::WrongDimension {
+                expected,
+                unit: unit.to_owned(),
+            }
+        } else {
+            ParseQuantityError::UnknownUnit(unit.to_owned())
+        }
+    }
+
+    /// Parses a length given in any known length unit into `D`.
+    pub fn parse_length<D: LengthUnit>(text: &str) -> Result<D, ParseQuantityError> {
+        let (value, unit) = split_quantity(text)?;
+        length_in(value, unit)
+    }
+
+    /// Parses a time given in any known time unit into `T`.
+    pub fn parse_time<T: TimeUnit>(text: &str) -> Result<T, ParseQuantityError> {
+        let (value, unit) = split_quantity(text)?;
+        time_in(value, unit)
+    }
+
+    /// Converts a length given with the unit symbol into `D`, refusing
+    /// lengths which aren't finite or are negative.
+    pub fn length_in<D: LengthUnit>(value: f32, unit: &str) -> Result<D, ParseQuantityError> {
+        let length = if unit == D::SYMBOL {
+            // Avoid rounding errors from converting to meters and back.
+            D::new(value)
+        } else {
+            let factor = length_factor(unit, Dimension::Length)?;
+            D::from_meters(value * factor)
+        };
+        QuantityError::check(length.value()).map_err(ParseQuantityError::InvalidQuantity)?;
+
+        Ok(length)
+    }
+
+    /// Converts a time given with the unit symbol into `T`, refusing
+    /// times which aren't finite or are negative.
+    pub fn time_in<T: TimeUnit>(value: f32, unit: &str) -> Result<T, ParseQuantityError> {
+        let time = if unit == T::SYMBOL {
+            T::new(value)
+        } else {
+            let factor = time_factor(unit, Dimension::Time)?;
+            T::from_seconds(value * factor)
+        };
+        QuantityError::check(time.value()).map_err(ParseQuantityError::InvalidQuantity)?;
+
+        Ok(time)
+    }
+
+    /// Prints the speed as distance unit per one time unit, e.g. "120 km/h".
+    impl<Dist: LengthUnit, Time: TimeUnit> fmt::Display for Speed<Dist, Time> {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            let value = self.distance.value() / self.time.value();
+            write!(f, "{value} {}/{}", Dist::SYMBOL, Time::SYMBOL)
+        }
+    }
+
+    impl<Dist: LengthUnit, Time: TimeUnit> FromStr for Speed<Dist, Time> {
+        type Err = ParseQuantityError;
+
+        fn from_str(s: &str) -> Result<Self, Self::Err> {
+            let (value, unit) = split_quantity(s)?;
+            let Some((dist_unit, time_unit)) = unit.split_once('/') else {
+                let is_known =
+                    lookup(LENGTH_UNITS, unit).is_some() || lookup(TIME_UNITS, unit).is_some();
+                return Err(unit_error(unit, Dimension::Speed, is_known));
+            };
+
+            let (dist_unit, time_unit) = (dist_unit.trim(), time_unit.trim());
+            let speed = if dist_unit == Dist::SYMBOL && time_unit == Time::SYMBOL {
+                Speed::new(Dist::new(value), Time::new(1.0))
+            } else {
+                let meters = value * length_factor(dist_unit, Dimension::Speed)?;
+                let seconds = time_factor(time_unit, Dimension::Speed)?;
+                Speed::new(Dist::from_meters(meters), Time::from_seconds(seconds))
+            };
+            speed.map_err(ParseQuantityError::InvalidQuantity)
         }
     }
 
@@ -154,4 +772,215 @@ mod generics {
         let time_seconds = Hour(20.0);
         let meter_per_second = Speed::new(distance_meter, time_seconds);
     }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn speed_in_both_units() {
+            let speed = Speed::new(Kilometer(36.0), Hour(1.0)).unwrap();
+            assert_eq!(speed.meter_per_second(), 10.0);
+            assert_eq!(speed.kilometer_per_hour(), 36.0);
+
+            let speed = Speed::new(Meter(10.0), Second(1.0)).unwrap();
+            assert_eq!(speed.meter_per_second(), 10.0);
+            assert_eq!(speed.kilometer_per_hour(), 36.0);
+        }
+
+        #[test]
+        fn compare_across_units() {
+            let fast = Speed::new(Kilometer(36.0), Hour(1.0)).unwrap();
+            let same = Speed::new(Meter(10.0), Second(1.0)).unwrap();
+            let slow = Speed::new(Meter(5.0), Second(1.0)).unwrap();
+
+            assert!(fast == same);
+            assert!(slow < fast);
+            assert!(fast > slow);
+        }
+
+        #[test]
+        fn validated_speed() {
+            assert!(matches!(
+                Speed::new(Meter(10.0), Second(0.0)),
+                Err(QuantityError::ZeroTime)
+            ));
+            assert!(matches!(
+                Speed::new(Meter(-10.0), Second(1.0)),
+                Err(QuantityError::Negative(_))
+            ));
+            assert!(matches!(
+                Speed::new(Meter(10.0), Second(-0.0)),
+                Err(QuantityError::Negative(_))
+            ));
+            assert!(matches!(
+                Speed::new(Kilometer(f32::NAN), Hour(1.0)),
+                Err(QuantityError::NotFinite(_))
+            ));
+            assert!(matches!(
+                Speed::new(Kilometer(f32::MAX), Hour(0.5)),
+                Err(QuantityError::NotFinite(_))
+            ));
+            assert!(matches!(
+                "-5 km/h".parse::<Speed<Kilometer, Hour>>(),
+                Err(ParseQuantityError::InvalidQuantity(
+                    QuantityError::Negative(_)
+                ))
+            ));
+        }
+
+        #[test]
+        fn convert_units() {
+            let speed = Speed::new(Meter(500.0), Second(1800.0)).unwrap();
+            let converted: Speed<Kilometer, Hour> = speed.convert().unwrap();
+
+            assert_eq!(converted.distance(), &Kilometer(0.5));
+            assert_eq!(converted.time(), &Hour(0.5));
+            assert!(converted == speed);
+
+            let huge = Speed::new(Kilometer(f32::MAX), Hour(1.0)).unwrap();
+            assert!(matches!(
+                huge.convert::<Meter, Second>(),
+                Err(QuantityError::NotFinite(_))
+            ));
+        }
+
+        #[test]
+        fn parse_quantities() {
+            assert_eq!("10 km".parse(), Ok(Kilometer(10.0)));
+            assert_eq!("3.5 h".parse(), Ok(Hour(3.5)));
+            assert_eq!("250m".parse(), Ok(Meter(250.0)));
+            assert_eq!("2 km".parse(), Ok(Meter(2000.0)));
+            assert_eq!(" 90 s ".parse(), Ok(Second(90.0)));
+            assert_eq!("1e3km".parse(), Ok(Kilometer(1000.0)));
+            assert_eq!("1.5e-3 h".parse(), Ok(Hour(0.0015)));
+
+            let speed: Speed<Kilometer, Hour> = "120 km/h".parse().unwrap();
+            assert_eq!(speed.kilometer_per_hour(), 120.0);
+
+            let speed: Speed<Meter, Second> = "36 km/h".parse().unwrap();
+            assert_eq!(speed.meter_per_second(), 10.0);
+        }
+
+        #[test]
+        fn parse_errors() {
+            assert_eq!(
+                "10 furlong".parse::<Meter>(),
+                Err(ParseQuantityError::UnknownUnit("furlong".into()))
+            );
+            assert_eq!(
+                "10 h".parse::<Meter>(),
+                Err(ParseQuantityError::WrongDimension {
+                    expected: Dimension::Length,
+                    unit: "h".into()
+                })
+            );
+            assert_eq!(
+                "10 km".parse::<Speed<Kilometer, Hour>>(),
+                Err(ParseQuantityError::WrongDimension {
+                    expected: Dimension::Speed,
+                    unit: "km".into()
+                })
+            );
+            assert_eq!("10".parse::<Hour>(), Err(ParseQuantityError::MissingUnit));
+            assert!(matches!(
+                "ten km".parse::<Kilometer>(),
+                Err(ParseQuantityError::InvalidNumber(_))
+            ));
+            assert!(matches!(
+                "NaN km".parse::<Kilometer>(),
+                Err(ParseQuantityError::InvalidQuantity(
+                    QuantityError::NotFinite(_)
+                ))
+            ));
+            assert_eq!(
+                "-5 km".parse::<Kilometer>(),
+                Err(ParseQuantityError::InvalidQuantity(
+                    QuantityError::Negative(-5.0)
+                ))
+            );
+            assert!(matches!(
+                "1e38 km".parse::<Meter>(),
+                Err(ParseQuantityError::InvalidQuantity(
+                    QuantityError::NotFinite(_)
+                ))
+            ));
+        }
+
+        #[test]
+        fn display_quantities() {
+            assert_eq!(Kilometer(10.0).to_string(), "10 km");
+            assert_eq!(Hour(3.5).to_string(), "3.5 h");
+            assert_eq!(
+                Speed::new(Kilometer(240.0), Hour(2.0)).unwrap().to_string(),
+                "120 km/h"
+            );
+        }
+
+        crate::unit!(NauticalMile: Length = 1852.0 * Meter, symbol = "nmi");
+        crate::unit!(Minute: Time = 60.0 * Second, symbol = "min");
+
+        #[test]
+        fn domain_units() {
+            assert_eq!("2 nmi".parse(), Ok(NauticalMile(2.0)));
+            assert_eq!("1852 m".parse(), Ok(NauticalMile(1.0)));
+            assert_eq!(NauticalMile(3.5).to_string(), "3.5 nmi");
+            // Defined units are only known when parsing into them.
+            assert_eq!(
+                "1 nmi".parse::<Meter>(),
+                Err(ParseQuantityError::UnknownUnit("nmi".into()))
+            );
+            assert_eq!(Meter::from(NauticalMile(1.0)), Meter(1852.0));
+            assert_eq!(Minute::from(Second(90.0)), Minute(1.5));
+
+            let knots = Speed::new(NauticalMile(10.0), Hour(1.0)).unwrap();
+            assert_eq!(knots.to_string(), "10 nmi/h");
+            assert!(knots == Speed::new(Meter(18520.0), Minute(60.0)).unwrap());
+        }
+
+        use proptest::prelude::*;
+
+        fn finite() -> impl Strategy<Value = f32> {
+            prop::num::f32::NORMAL | prop::num::f32::SUBNORMAL | prop::num::f32::ZERO
+        }
+
+        fn positive() -> impl Strategy<Value = f32> {
+            prop::num::f32::POSITIVE | prop::num::f32::NORMAL
+        }
+
+        proptest! {
+            #[test]
+            /// Ensure parsing doesn't crash on any input.
+            fn parse_no_crash(s in "\\PC*") {
+                let _ = s.parse::<Meter>();
+                let _ = s.parse::<Speed<Kilometer, Hour>>();
+            }
+
+            #[test]
+            /// Ensure printed units are parsed back to the same value.
+            fn units_round_trip(value in finite().prop_map(f32::abs)) {
+                prop_assert_eq!(Meter(value).to_string().parse(), Ok(Meter(value)));
+                prop_assert_eq!(Kilometer(value).to_string().parse(), Ok(Kilometer(value)));
+                prop_assert_eq!(Second(value).to_string().parse(), Ok(Second(value)));
+                prop_assert_eq!(Hour(value).to_string().parse(), Ok(Hour(value)));
+            }
+
+            #[test]
+            /// Ensure printed speeds are parsed back to the same speed.
+            fn speed_round_trip(distance in finite().prop_map(f32::abs), time in positive()) {
+                match Speed::new(Kilometer(distance), Hour(time)) {
+                    Ok(speed) => {
+                        let text = speed.to_string();
+                        let parsed: Speed<Kilometer, Hour> = text.parse().unwrap();
+                        prop_assert_eq!(parsed.to_string(), text);
+                    }
+                    // Speeds beyond the range of `f32` are refused instead of printed as "inf".
+                    Err(err) => {
+                        prop_assert!(matches!(err, QuantityError::NotFinite(_)));
+                        prop_assert!(!(distance / time).is_finite());
+                    }
+                }
+            }
+        }
+    }
 }
@@ -0,0 +1,474 @@
+//! Reads CSV files with units in their headers into typed records.
+//!
+//! The header names the unit of the distance and time columns, e.g.
+//! `distance[km],time[h]`, and the values are converted into the units
+//! requested by the caller. Other columns are ignored. Units defined with
+//! [`unit!`](crate::unit) are only known in the header when they are the
+//! requested unit.
+//!
+//! Fields can be quoted to contain commas, with quotes inside written
+//! twice as in `"say ""hi"", bye"`. Quoted fields can't span lines.
+//!
+//! ```
+//! use present::new_type::{csv, generics::{Meter, Second}};
+//!
+//! let input = "distance[km],time[h]\n36,1\n";
+//! let records = csv::read_records::<Meter, Second, _>(input.as_bytes()).unwrap();
+//!
+//! let record = records.map(Result::unwrap).next().unwrap();
+//! assert_eq!(record.speed().unwrap().meter_per_second(), 10.0);
+//! ```
+
+use std::{
+    fmt,
+    io::{self, BufRead, Lines},
+    marker::PhantomData,
+    num::ParseFloatError,
+};
+
+use super::{
+    generics::{length_in, time_in, LengthUnit, ParseQuantityError, Speed, TimeUnit},
+    good::QuantityError,
+};
+
+const DISTANCE_COLUMN: &str = "distance";
+const TIME_COLUMN: &str = "time";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Record<Dist, Time> {
+    pub distance: Dist,
+    pub time: Time,
+}
+
+impl<Dist: LengthUnit + Copy, Time: TimeUnit + Copy> Record<Dist, Time> {
+    /// Speed of the record, failing for e.g. a zero time.
+    pub fn speed(&self) -> Result<Speed<Dist, Time>, QuantityError> {
+        Speed::new(self.distance, self.time)
+    }
+}
+
+/// Errors in the header which prevent reading any row.
+#[derive(Debug)]
+pub enum HeaderError {
+    Io(io::Error),
+    /// The input has no header line.
+    Empty,
+    MissingColumn(&'static str),
+    /// The column is in the header more than once.
+    DuplicateColumn(&'static str),
+    /// Distance or time column header without unit in the form `name[unit]`.
+    MissingUnit(String),
+    Unit {
+        column: &'static str,
+        source: ParseQuantityError,
+    },
+}
+
+impl fmt::Display for HeaderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HeaderError::Io(err) => write!(f, "Reading header failed: {err}"),
+            HeaderError::Empty => f.write_str("Input has no header"),
+            HeaderError::MissingColumn(column) => write!(f, "Column '{column}' is missing"),
+            HeaderError::DuplicateColumn(column) => {
+                write!(f, "Column '{column}' is given more than once")
+            }
+            HeaderError::MissingUnit(header) => {
+                write!(f, "Column '{header}' has no unit. Expected 'name[unit]'")
+            }
+            HeaderError::Unit { column, source } => {
+                write!(f, "Invalid unit for column '{column}': {source}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for HeaderError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            HeaderError::Io(err) => Some(err),
+            HeaderError::Unit { source, .. } => Some(source),
+            HeaderError::Empty
+            | HeaderError::MissingColumn(_)
+            | HeaderError::DuplicateColumn(_)
+            | HeaderError::MissingUnit(_) => None,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum RowErrorKind {
+    Io(io::Error),
+    MissingValue(&'static str),
+    InvalidNumber {
+        column: &'static str,
+        source: ParseFloatError,
+    },
+    /// The number isn't a valid quantity, e.g. a negative time.
+    InvalidQuantity {
+        column: &'static str,
+        source: QuantityError,
+    },
+}
+
+/// Error of a single row, which doesn't stop reading the following rows.
+#[derive(Debug)]
+pub struct RowError {
+    /// Line number starting from one for the header.
+    pub line: usize,
+    pub kind: RowErrorKind,
+}
+
+impl fmt::Display for RowError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let line = self.line;
+        match &self.kind {
+            RowErrorKind::Io(err) => write!(f, "Line {line}: Reading failed: {err}"),
+            RowErrorKind::MissingValue(column) => {
+                write!(f, "Line {line}: Value for column '{column}' is missing")
+            }
+            RowErrorKind::InvalidNumber { column, source } => {
+                write!(
+                    f,
+                    "Line {line}: Invalid number in column '{column}': {source}"
+                )
+            }
+            RowErrorKind::InvalidQuantity { column, source } => {
+                write!(
+                    f,
+                    "Line {line}: Invalid quantity in column '{column}': {source}"
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for RowError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match &self.kind {
+            RowErrorKind::Io(err) => Some(err),
+            RowErrorKind::InvalidNumber { source, .. } => Some(source),
+            RowErrorKind::InvalidQuantity { source, .. } => Some(source),
+            RowErrorKind::MissingValue(_) => None,
+        }
+    }
+}
+
+/// Position and unit of a column in the CSV file.
+#[derive(Debug)]
+struct Column {
+    index: usize,
+    unit: String,
+}
+
+/// Iterator over the records of a CSV file.
+pub struct Records<Dist, Time, R> {
+    lines: Lines<R>,
+    line: usize,
+    distance: Column,
+    time: Column,
+    units: PhantomData<(Dist, Time)>,
+}
+
+/// Reads the header and returns an iterator over the records, converting
+/// the values into the units `Dist` and `Time`.
+pub fn read_records<Dist, Time, R>(reader: R) -> Result<Records<Dist, Time, R>, HeaderError>
+where
+    Dist: LengthUnit,
+    Time: TimeUnit,
+    R: BufRead,
+{
+    let mut lines = reader.lines();
+    let header = lines
+        .next()
+        .ok_or(HeaderError::Empty)?
+        .map_err(HeaderError::Io)?;
+
+    let mut distance = None;
+    let mut time = None;
+    for (index, header) in split_fields(&header).iter().enumerate() {
+        let header = header.trim();
+        let (name, unit) = match header
+            .strip_suffix(']')
+            .and_then(|header| header.split_once('['))
+        {
+            Some((name, unit)) => (name.trim(), Some(unit.trim())),
+            None => (header, None),
+        };
+
+        let (column_name, column) = match name {
+            DISTANCE_COLUMN => (DISTANCE_COLUMN, &mut distance),
+            TIME_COLUMN => (TIME_COLUMN, &mut time),
+            // Other columns are ignored.
+            _ => continue,
+        };
+        let unit = unit.ok_or_else(|| HeaderError::MissingUnit(header.to_owned()))?;
+        if column.is_some() {
+            return Err(HeaderError::DuplicateColumn(column_name));
+        }
+        *column = Some(Column {
+            index,
+            unit: unit.to_owned(),
+        });
+    }
+
+    let distance = distance.ok_or(HeaderError::MissingColumn(DISTANCE_COLUMN))?;
+    let time = time.ok_or(HeaderError::MissingColumn(TIME_COLUMN))?;
+
+    // Validate units once so rows can't fail because of them.
+    length_in::<Dist>(0.0, &distance.unit).map_err(|source| HeaderError::Unit {
+        column: DISTANCE_COLUMN,
+        source,
+    })?;
+    time_in::<Time>(0.0, &time.unit).map_err(|source| HeaderError::Unit {
+        column: TIME_COLUMN,
+        source,
+    })?;
+
+    Ok(Records {
+        lines,
+        line: 1,
+        distance,
+        time,
+        units: PhantomData,
+    })
+}
+
+/// Splits the line at commas outside of quoted fields and unquotes them.
+fn split_fields(line: &str) -> Vec<String> {
+    let mut fields = vec![String::new()];
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        let field = fields.last_mut().expect("There is always a current field");
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(String::new()),
+            c => field.push(c),
+        }
+    }
+    fields
+}
+
+fn parse_value(cells: &[String], column: &'static str, index: usize) -> Result<f32, RowErrorKind> {
+    cells
+        .get(index)
+        .ok_or(RowErrorKind::MissingValue(column))?
+        .trim()
+        .parse()
+        .map_err(|source| RowErrorKind::InvalidNumber { column, source })
+}
+
+/// Converts the error of a value in the unit of its column. Units are
+/// validated with the header, so only the value itself can be invalid.
+fn invalid_quantity(column: &'static str, err: ParseQuantityError) -> RowErrorKind {
+    match err {
+        ParseQuantityError::InvalidQuantity(source) => {
+            RowErrorKind::InvalidQuantity { column, source }
+        }
+        err => unreachable!("Unit of column '{column}' is validated with header: {err}"),
+    }
+}
+
+impl<Dist, Time, R> Records<Dist, Time, R>
+where
+    Dist: LengthUnit,
+    Time: TimeUnit,
+{
+    fn parse_row(&self, row: &str) -> Result<Record<Dist, Time>, RowErrorKind> {
+        let cells = split_fields(row);
+        let distance = parse_value(&cells, DISTANCE_COLUMN, self.distance.index)?;
+        let time = parse_value(&cells, TIME_COLUMN, self.time.index)?;
+
+        let distance = length_in(distance, &self.distance.unit)
+            .map_err(|err| invalid_quantity(DISTANCE_COLUMN, err))?;
+        let time =
+            time_in(time, &self.time.unit).map_err(|err| invalid_quantity(TIME_COLUMN, err))?;
+
+        Ok(Record { distance, time })
+    }
+}
+
+impl<Dist, Time, R> Iterator for Records<Dist, Time, R>
+where
+    Dist: LengthUnit,
+    Time: TimeUnit,
+    R: BufRead,
+{
+    type Item = Result<Record<Dist, Time>, RowError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let line = self.lines.next()?;
+            self.line += 1;
+
+            let result = match line {
+                // Skip empty lines.
+                Ok(row) if row.trim().is_empty() => continue,
+                Ok(row) => self.parse_row(&row),
+                Err(err) => Err(RowErrorKind::Io(err)),
+            };
+
+            return Some(result.map_err(|kind| RowError {
+                line: self.line,
+                kind,
+            }));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::new_type::generics::{Hour, Kilometer, Meter, Second};
+
+    #[test]
+    fn keep_units() {
+        let input = "distance[km],time[h]\n10,2\n3.5,0.5\n";
+        let records: Vec<_> = read_records::<Kilometer, Hour, _>(input.as_bytes())
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+
+        assert_eq!(
+            records,
+            vec![
+                Record {
+                    distance: Kilometer(10.0),
+                    time: Hour(2.0)
+                },
+                Record {
+                    distance: Kilometer(3.5),
+                    time: Hour(0.5)
+                }
+            ]
+        );
+        assert_eq!(records[0].speed().unwrap().kilometer_per_hour(), 5.0);
+    }
+
+    #[test]
+    fn normalize_units() {
+        let input = "name, time[h], distance[km]\nfirst,0.5,2\n";
+        let record = read_records::<Meter, Second, _>(input.as_bytes())
+            .unwrap()
+            .next()
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(record.distance, Meter(2000.0));
+        assert_eq!(record.time, Second(1800.0));
+    }
+
+    #[test]
+    fn quoted_fields() {
+        let input = "\"name, first\",distance[m],time[s]\n\"Doe, \"\"J\"\"\",10,2\n";
+        let record = read_records::<Meter, Second, _>(input.as_bytes())
+            .unwrap()
+            .next()
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(record.distance, Meter(10.0));
+        assert_eq!(record.time, Second(2.0));
+        assert_eq!(split_fields("a,\"b, \"\"c\"\"\",d"), ["a", "b, \"c\"", "d"]);
+    }
+
+    #[test]
+    fn row_errors() {
+        let input = "distance[m],time[s]\n10,2\n\nten,2\n5\n20,4\n";
+        let results: Vec<_> = read_records::<Meter, Second, _>(input.as_bytes())
+            .unwrap()
+            .collect();
+
+        assert_eq!(results.len(), 4);
+        assert!(results[0].is_ok());
+
+        let err = results[1].as_ref().unwrap_err();
+        assert_eq!(err.line, 4);
+        assert!(matches!(
+            err.kind,
+            RowErrorKind::InvalidNumber {
+                column: DISTANCE_COLUMN,
+                ..
+            }
+        ));
+
+        let err = results[2].as_ref().unwrap_err();
+        assert_eq!(err.line, 5);
+        assert!(matches!(err.kind, RowErrorKind::MissingValue(TIME_COLUMN)));
+
+        assert!(results[3].is_ok());
+    }
+
+    #[test]
+    fn invalid_quantities() {
+        let input = "distance[km],time[h]\nNaN,1\n10,-2\ninf,1\n1e38,1\n";
+        let errors: Vec<_> = read_records::<Meter, Second, _>(input.as_bytes())
+            .unwrap()
+            .map(|result| result.unwrap_err().kind)
+            .collect();
+
+        assert_eq!(errors.len(), 4);
+        assert!(matches!(
+            errors[0],
+            RowErrorKind::InvalidQuantity {
+                column: DISTANCE_COLUMN,
+                source: QuantityError::NotFinite(_)
+            }
+        ));
+        assert!(matches!(
+            errors[1],
+            RowErrorKind::InvalidQuantity {
+                column: TIME_COLUMN,
+                source: QuantityError::Negative(_)
+            }
+        ));
+        // Values can also overflow when converted to the requested unit.
+        for err in &errors[2..] {
+            assert!(matches!(
+                err,
+                RowErrorKind::InvalidQuantity {
+                    column: DISTANCE_COLUMN,
+                    source: QuantityError::NotFinite(_)
+                }
+            ));
+        }
+    }
+
+    #[test]
+    fn header_errors() {
+        let read = |input: &str| read_records::<Meter, Second, _>(input.as_bytes()).err();
+
+        assert!(matches!(read(""), Some(HeaderError::Empty)));
+        assert!(matches!(
+            read("distance[m]\n"),
+            Some(HeaderError::MissingColumn(TIME_COLUMN))
+        ));
+        assert!(matches!(
+            read("distance,time[s]\n"),
+            Some(HeaderError::MissingUnit(header)) if header == "distance"
+        ));
+        assert!(matches!(
+            read("distance[m],time[s],distance[km]\n"),
+            Some(HeaderError::DuplicateColumn(DISTANCE_COLUMN))
+        ));
+        assert!(matches!(
+            read("distance[h],time[s]\n"),
+            Some(HeaderError::Unit {
+                column: DISTANCE_COLUMN,
+                source: ParseQuantityError::WrongDimension { .. }
+            })
+        ));
+        assert!(matches!(
+            read("distance[m],time[fortnight]\n"),
+            Some(HeaderError::Unit {
+                column: TIME_COLUMN,
+                source: ParseQuantityError::UnknownUnit(_)
+            })
+        ));
+    }
+}
@@ -0,0 +1,198 @@
+//! Geographic coordinates where latitude and longitude can't be swapped.
+
+use std::fmt;
+
+use super::good::Meter;
+
+/// Mean radius of the earth used for the haversine formula.
+const EARTH_RADIUS_METERS: f64 = 6_371_008.8;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GeoError {
+    /// Latitude must be within ±90 degrees.
+    LatitudeOutOfRange(f64),
+    /// Longitude must be within ±180 degrees.
+    LongitudeOutOfRange(f64),
+}
+
+impl fmt::Display for GeoError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GeoError::LatitudeOutOfRange(value) => {
+                write!(f, "Latitude must be within ±90 degrees, got {value}")
+            }
+            GeoError::LongitudeOutOfRange(value) => {
+                write!(f, "Longitude must be within ±180 degrees, got {value}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for GeoError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+pub struct Degrees(pub f64);
+
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+pub struct Radians(pub f64);
+
+impl From<Degrees> for Radians {
+    fn from(value: Degrees) -> Self {
+        Radians(value.0.to_radians())
+    }
+}
+
+impl From<Radians> for Degrees {
+    fn from(value: Radians) -> Self {
+        Degrees(value.0.to_degrees())
+    }
+}
+
+/// Latitude in degrees, north is positive.
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+pub struct Latitude(f64);
+
+/// Longitude in degrees, east is positive.
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+pub struct Longitude(f64);
+
+impl Latitude {
+    pub fn degrees(&self) -> Degrees {
+        Degrees(self.0)
+    }
+}
+
+impl Longitude {
+    pub fn degrees(&self) -> Degrees {
+        Degrees(self.0)
+    }
+}
+
+impl TryFrom<f64> for Latitude {
+    type Error = GeoError;
+
+    fn try_from(value: f64) -> Result<Self, Self::Error> {
+        // Range check rejects NaN too.
+        if (-90.0..=90.0).contains(&value) {
+            Ok(Self(value))
+        } else {
+            Err(GeoError::LatitudeOutOfRange(value))
+        }
+    }
+}
+
+impl TryFrom<f64> for Longitude {
+    type Error = GeoError;
+
+    fn try_from(value: f64) -> Result<Self, Self::Error> {
+        if (-180.0..=180.0).contains(&value) {
+            Ok(Self(value))
+        } else {
+            Err(GeoError::LongitudeOutOfRange(value))
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GeoPoint {
+    latitude: Latitude,
+    longitude: Longitude,
+}
+
+impl GeoPoint {
+    pub fn new(latitude: Latitude, longitude: Longitude) -> Self {
+        Self {
+            latitude,
+            longitude,
+        }
+    }
+
+    pub fn latitude(&self) -> Latitude {
+        self.latitude
+    }
+
+    pub fn longitude(&self) -> Longitude {
+        self.longitude
+    }
+
+    /// Great-circle distance to the other point using the haversine formula.
+    pub fn distance(&self, other: &GeoPoint) -> Meter {
+        let Radians(lat1) = self.latitude.degrees().into();
+        let Radians(lat2) = other.latitude.degrees().into();
+        let delta_lat = lat2 - lat1;
+        let delta_lon = (other.longitude.0 - self.longitude.0).to_radians();
+
+        let a = (delta_lat / 2.0).sin().powi(2)
+            + lat1.cos() * lat2.cos() * (delta_lon / 2.0).sin().powi(2);
+        let central_angle = 2.0 * a.sqrt().atan2((1.0 - a).sqrt());
+
+        Meter((EARTH_RADIUS_METERS * central_angle) as f32)
+    }
+
+    /// Initial bearing toward the other point, clockwise from north
+    /// within `[0, 360)` degrees.
+    pub fn bearing(&self, other: &GeoPoint) -> Degrees {
+        let Radians(lat1) = self.latitude.degrees().into();
+        let Radians(lat2) = other.latitude.degrees().into();
+        let delta_lon = (other.longitude.0 - self.longitude.0).to_radians();
+
+        let y = delta_lon.sin() * lat2.cos();
+        let x = lat1.cos() * lat2.sin() - lat1.sin() * lat2.cos() * delta_lon.cos();
+        let Degrees(bearing) = Radians(y.atan2(x)).into();
+
+        Degrees(bearing.rem_euclid(360.0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn point(latitude: f64, longitude: f64) -> GeoPoint {
+        GeoPoint::new(latitude.try_into().unwrap(), longitude.try_into().unwrap())
+    }
+
+    #[test]
+    fn validate_ranges() {
+        assert!(Latitude::try_from(90.0).is_ok());
+        assert!(Longitude::try_from(-180.0).is_ok());
+        assert_eq!(
+            Latitude::try_from(91.0),
+            Err(GeoError::LatitudeOutOfRange(91.0))
+        );
+        assert_eq!(
+            Longitude::try_from(180.5),
+            Err(GeoError::LongitudeOutOfRange(180.5))
+        );
+        assert!(Latitude::try_from(f64::NAN).is_err());
+    }
+
+    #[test]
+    fn angle_conversion() {
+        let Radians(radians) = Degrees(180.0).into();
+        assert_eq!(radians, std::f64::consts::PI);
+
+        let Degrees(degrees) = Radians(std::f64::consts::FRAC_PI_2).into();
+        assert_eq!(degrees, 90.0);
+    }
+
+    #[test]
+    fn distance() {
+        let berlin = point(52.5200, 13.4050);
+        let paris = point(48.8566, 2.3522);
+
+        let distance = berlin.distance(&paris).value();
+        assert!((distance - 877_460.0).abs() < 1000.0, "{distance}");
+        assert_eq!(berlin.distance(&berlin).value(), 0.0);
+    }
+
+    #[test]
+    fn bearing() {
+        let origin = point(0.0, 0.0);
+
+        assert_eq!(origin.bearing(&point(10.0, 0.0)), Degrees(0.0));
+        assert_eq!(origin.bearing(&point(0.0, 10.0)), Degrees(90.0));
+        assert_eq!(origin.bearing(&point(-10.0, 0.0)), Degrees(180.0));
+        assert_eq!(origin.bearing(&point(0.0, -10.0)), Degrees(270.0));
+    }
+}
@@ -0,0 +1,205 @@
+//! Opaque identifiers which can't be mixed up with each other.
+//!
+//! `UserId` and `OrderId` can both be `u64`, but passing one where the
+//! other is expected must be a compiler error:
+//!
+//! ```
+//! use present::new_type::id::{Id, IdTag};
+//!
+//! enum User {}
+//! impl IdTag for User {
+//!     const PREFIX: &'static str = "usr";
+//! }
+//! type UserId = Id<User>;
+//!
+//! let id: UserId = "usr_123".parse().unwrap();
+//! assert_eq!(id, UserId::new(123));
+//! assert_eq!(id.to_string(), "usr_123");
+//! ```
+
+use std::{
+    cmp::Ordering,
+    fmt,
+    hash::{Hash, Hasher},
+    marker::PhantomData,
+    num::ParseIntError,
+    str::FromStr,
+};
+
+/// Marker for the kind of an identifier.
+pub trait IdTag {
+    /// Prefix used when printing and parsing the identifier, separated
+    /// with `_` from the number. Empty prefix prints the number only.
+    const PREFIX: &'static str = "";
+}
+
+/// Identifier of the kind `Tag`.
+///
+/// Traits are implemented manually, because deriving them would require
+/// the tag to implement them too.
+pub struct Id<Tag> {
+    value: u64,
+    // Function pointer keeps the id `Send` and `Sync` regardless of the tag.
+    tag: PhantomData<fn() -> Tag>,
+}
+
+impl<Tag> Id<Tag> {
+    pub const fn new(value: u64) -> Self {
+        Self {
+            value,
+            tag: PhantomData,
+        }
+    }
+
+    pub fn value(&self) -> u64 {
+        self.value
+    }
+}
+
+impl<Tag> Clone for Id<Tag> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<Tag> Copy for Id<Tag> {}
+
+impl<Tag> PartialEq for Id<Tag> {
+    fn eq(&self, other: &Self) -> bool {
+        self.value == other.value
+    }
+}
+
+impl<Tag> Eq for Id<Tag> {}
+
+impl<Tag> PartialOrd for Id<Tag> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<Tag> Ord for Id<Tag> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.value.cmp(&other.value)
+    }
+}
+
+impl<Tag> Hash for Id<Tag> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.value.hash(state);
+    }
+}
+
+impl<Tag: IdTag> fmt::Debug for Id<Tag> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Id({self})")
+    }
+}
+
+impl<Tag: IdTag> fmt::Display for Id<Tag> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if Tag::PREFIX.is_empty() {
+            write!(f, "{}", self.value)
+        } else {
+            write!(f, "{}_{}", Tag::PREFIX, self.value)
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseIdError {
+    /// The text doesn't start with the prefix of the identifier.
+    WrongPrefix {
+        expected: &'static str,
+    },
+    InvalidNumber(ParseIntError),
+}
+
+impl fmt::Display for ParseIdError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseIdError::WrongPrefix { expected } => {
+                write!(f, "Identifier must start with '{expected}_'")
+            }
+            ParseIdError::InvalidNumber(err) => write!(f, "Invalid identifier number: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for ParseIdError {}
+
+impl<Tag: IdTag> FromStr for Id<Tag> {
+    type Err = ParseIdError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let number = if Tag::PREFIX.is_empty() {
+            s
+        } else {
+            s.strip_prefix(Tag::PREFIX)
+                .and_then(|rest| rest.strip_prefix('_'))
+                .ok_or(ParseIdError::WrongPrefix {
+                    expected: Tag::PREFIX,
+                })?
+        };
+
+        number
+            .parse()
+            .map(Self::new)
+            .map_err(ParseIdError::InvalidNumber)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+
+    enum User {}
+    impl IdTag for User {
+        const PREFIX: &'static str = "usr";
+    }
+    type UserId = Id<User>;
+
+    enum Order {}
+    impl IdTag for Order {}
+    type OrderId = Id<Order>;
+
+    #[test]
+    fn display_and_parse() {
+        assert_eq!(UserId::new(42).to_string(), "usr_42");
+        assert_eq!("usr_42".parse(), Ok(UserId::new(42)));
+        assert_eq!(OrderId::new(7).to_string(), "7");
+        assert_eq!("7".parse(), Ok(OrderId::new(7)));
+        assert_eq!(format!("{:?}", UserId::new(1)), "Id(usr_1)");
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert_eq!(
+            "ord_42".parse::<UserId>(),
+            Err(ParseIdError::WrongPrefix { expected: "usr" })
+        );
+        assert_eq!(
+            "usr42".parse::<UserId>(),
+            Err(ParseIdError::WrongPrefix { expected: "usr" })
+        );
+        assert!(matches!(
+            "usr_abc".parse::<UserId>(),
+            Err(ParseIdError::InvalidNumber(_))
+        ));
+    }
+
+    #[test]
+    fn ordering_and_hashing() {
+        let mut ids = vec![UserId::new(3), UserId::new(1), UserId::new(2)];
+        ids.sort();
+        assert_eq!(ids, [UserId::new(1), UserId::new(2), UserId::new(3)]);
+
+        let set: HashSet<_> = [UserId::new(1), UserId::new(1)].into_iter().collect();
+        assert_eq!(set.len(), 1);
+
+        // Compiler error. Yay!
+        // assert_eq!(UserId::new(1), OrderId::new(1));
+    }
+}
@@ -0,0 +1,112 @@
+/// Defines a unit newtype for [`generics`](crate::new_type::generics).
+///
+/// The generated type implements [`LengthUnit`](crate::new_type::generics::LengthUnit)
+/// or [`TimeUnit`](crate::new_type::generics::TimeUnit) depending on its dimension,
+/// `Display` and `FromStr` with the given symbol, and `From` conversions to and
+/// from the unit it's defined in.
+///
+/// Parsing knows the built-in units of [`generics`](crate::new_type::generics)
+/// and the symbol of the type parsed into. Defined units aren't registered
+/// with the parser, so `"1 nmi"` parses into `NauticalMile` but not into
+/// `Meter`, and CSV headers can only use them when reading into that unit.
+///
+/// ```
+/// use present::new_type::generics::{Hour, Meter, Speed};
+///
+/// present::unit!(NauticalMile: Length = 1852.0 * Meter, symbol = "nmi");
+/// type Knots = Speed<NauticalMile, Hour>;
+///
+/// let speed = Knots::new(NauticalMile(20.0), Hour(1.0)).unwrap();
+/// assert_eq!(speed.kilometer_per_hour(), 37.04);
+/// assert_eq!(Meter::from(NauticalMile(1.0)), Meter(1852.0));
+/// assert!("1 nmi".parse::<Meter>().is_err());
+/// ```
+///
+/// Units always need a factor to their base unit:
+///
+/// ```compile_fail
+/// present::unit!(Foot: Length, symbol = "ft");
+/// ```
+#[macro_export]
+macro_rules! unit {
+    // Base units are only defined by `generics`, the `@` arms are internal.
+    (@base $(#[$attr:meta])* $name:ident: Length, symbol = $symbol:literal) => {
+        $crate::unit!(
+            @define $(#[$attr])* $name, LengthUnit, METERS, parse_length, 1.0, $symbol,
+            concat!("Base unit of length with the symbol `", $symbol, "`.")
+        );
+    };
+    (@base $(#[$attr:meta])* $name:ident: Time, symbol = $symbol:literal) => {
+        $crate::unit!(
+            @define $(#[$attr])* $name, TimeUnit, SECONDS, parse_time, 1.0, $symbol,
+            concat!("Base unit of time with the symbol `", $symbol, "`.")
+        );
+    };
+    ($(#[$attr:meta])* $name:ident: Length = $factor:literal * $base:ident, symbol = $symbol:literal) => {
+        $crate::unit!(
+            @define $(#[$attr])* $name, LengthUnit, METERS, parse_length,
+            $factor * <$base as $crate::new_type::generics::LengthUnit>::METERS, $symbol,
+            concat!("Unit of length equal to ", stringify!($factor), " ", stringify!($base),
+                " with the symbol `", $symbol, "`.")
+        );
+        $crate::unit!(@convert $name, $base, LengthUnit, to_meters, from_meters);
+    };
+    ($(#[$attr:meta])* $name:ident: Time = $factor:literal * $base:ident, symbol = $symbol:literal) => {
+        $crate::unit!(
+            @define $(#[$attr])* $name, TimeUnit, SECONDS, parse_time,
+            $factor * <$base as $crate::new_type::generics::TimeUnit>::SECONDS, $symbol,
+            concat!("Unit of time equal to ", stringify!($factor), " ", stringify!($base),
+                " with the symbol `", $symbol, "`.")
+        );
+        $crate::unit!(@convert $name, $base, TimeUnit, to_seconds, from_seconds);
+    };
+    (@define $(#[$attr:meta])* $name:ident, $trait:ident, $factor_name:ident, $parse:ident,
+        $factor:expr, $symbol:literal, $doc:expr) => {
+        $(#[$attr])*
+        #[doc = $doc]
+        #[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+        pub struct $name(pub f32);
+
+        impl $crate::new_type::generics::$trait for $name {
+            const $factor_name: f32 = $factor;
+            const SYMBOL: &'static str = $symbol;
+
+            fn new(value: f32) -> Self {
+                Self(value)
+            }
+
+            fn value(&self) -> f32 {
+                self.0
+            }
+        }
+
+        impl ::std::fmt::Display for $name {
+            fn fmt(&self, f: &mut ::std::fmt::Formatter<'_>) -> ::std::fmt::Result {
+                write!(f, "{} {}", self.0, $symbol)
+            }
+        }
+
+        impl ::std::str::FromStr for $name {
+            type Err = $crate::new_type::generics::ParseQuantityError;
+
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                $crate::new_type::generics::$parse(s)
+            }
+        }
+    };
+    (@convert $name:ident, $base:ident, $trait:ident, $to_base:ident, $from_base:ident) => {
+        impl From<$base> for $name {
+            fn from(value: $base) -> Self {
+                use $crate::new_type::generics::$trait;
+                Self::$from_base(value.$to_base())
+            }
+        }
+
+        impl From<$name> for $base {
+            fn from(value: $name) -> Self {
+                use $crate::new_type::generics::$trait;
+                Self::$from_base(value.$to_base())
+            }
+        }
+    };
+}
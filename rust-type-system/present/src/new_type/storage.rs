@@ -0,0 +1,324 @@
+//! Quantities generic over the type storing their value.
+//!
+//! Floats are fine for short calculations, but long-running counters lose
+//! precision with them. Storing millimeters and milliseconds as integers
+//! keeps them exact while the units still can't be mixed up.
+
+use std::{
+    fmt,
+    ops::{Add, Sub},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StorageError {
+    /// Arithmetic result doesn't fit into the storage type.
+    Overflow,
+    /// Value is out of range of the storage type, or can't be represented
+    /// exactly where that is required.
+    NotRepresentable(f64),
+    /// Speed with zero time would be infinite.
+    ZeroTime,
+}
+
+impl fmt::Display for StorageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StorageError::Overflow => f.write_str("Arithmetic overflow"),
+            StorageError::NotRepresentable(value) => {
+                write!(f, "Value {value} can't be represented by the storage type")
+            }
+            StorageError::ZeroTime => f.write_str("Time of speed can't be zero"),
+        }
+    }
+}
+
+impl std::error::Error for StorageError {}
+
+/// Numeric type which can store the value of a quantity.
+pub trait Storage: Copy + PartialOrd + fmt::Debug {
+    fn checked_add(self, rhs: Self) -> Option<Self>;
+
+    fn checked_sub(self, rhs: Self) -> Option<Self>;
+
+    fn saturating_add(self, rhs: Self) -> Self;
+
+    fn saturating_sub(self, rhs: Self) -> Self;
+
+    fn to_f64(self) -> f64;
+
+    /// Converts the value if the storage type can hold it. Floats round to
+    /// the nearest value, while integers only accept values stored exactly.
+    fn from_f64(value: f64) -> Option<Self>;
+}
+
+macro_rules! float_storage {
+    ($($float:ty),*) => {$(
+        impl Storage for $float {
+            fn checked_add(self, rhs: Self) -> Option<Self> {
+                Some(self + rhs).filter(|sum| sum.is_finite())
+            }
+
+            fn checked_sub(self, rhs: Self) -> Option<Self> {
+                Some(self - rhs).filter(|diff| diff.is_finite())
+            }
+
+            fn saturating_add(self, rhs: Self) -> Self {
+                (self + rhs).clamp(<$float>::MIN, <$float>::MAX)
+            }
+
+            fn saturating_sub(self, rhs: Self) -> Self {
+                (self - rhs).clamp(<$float>::MIN, <$float>::MAX)
+            }
+
+            fn to_f64(self) -> f64 {
+                self as f64
+            }
+
+            fn from_f64(value: f64) -> Option<Self> {
+                let converted = value as $float;
+                converted.is_finite().then_some(converted)
+            }
+        }
+    )*};
+}
+
+macro_rules! int_storage {
+    ($($int:ty),*) => {$(
+        impl Storage for $int {
+            fn checked_add(self, rhs: Self) -> Option<Self> {
+                <$int>::checked_add(self, rhs)
+            }
+
+            fn checked_sub(self, rhs: Self) -> Option<Self> {
+                <$int>::checked_sub(self, rhs)
+            }
+
+            fn saturating_add(self, rhs: Self) -> Self {
+                <$int>::saturating_add(self, rhs)
+            }
+
+            fn saturating_sub(self, rhs: Self) -> Self {
+                <$int>::saturating_sub(self, rhs)
+            }
+
+            fn to_f64(self) -> f64 {
+                self as f64
+            }
+
+            fn from_f64(value: f64) -> Option<Self> {
+                // Casting saturates, so the round trip check catches
+                // out of range, fractional and non-finite values. The upper
+                // bound is needed since `MAX as f64` can round up.
+                let converted = value as $int;
+                let in_range = value < 2f64.powi(<$int>::BITS as i32);
+                (in_range && converted as f64 == value).then_some(converted)
+            }
+        }
+    )*};
+}
+
+float_storage!(f32, f64);
+int_storage!(u32, u64);
+
+/// Defines a quantity generic over its storage.
+///
+/// * `$per_base`: count of units in one base unit.
+macro_rules! quantity {
+    ($(#[$doc:meta])* $name:ident, $to_base:ident, $from_base:ident, $per_base:expr) => {
+        $(#[$doc])*
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+        pub struct $name<S = u64>(S);
+
+        impl<S: Storage> $name<S> {
+            pub fn new(value: S) -> Self {
+                Self(value)
+            }
+
+            pub fn value(&self) -> S {
+                self.0
+            }
+
+            pub fn checked_add(self, rhs: Self) -> Result<Self, StorageError> {
+                self.0.checked_add(rhs.0).map(Self).ok_or(StorageError::Overflow)
+            }
+
+            pub fn checked_sub(self, rhs: Self) -> Result<Self, StorageError> {
+                self.0.checked_sub(rhs.0).map(Self).ok_or(StorageError::Overflow)
+            }
+
+            /// Changes the storage type, failing if the value can't
+            /// be represented exactly by the new type.
+            pub fn try_cast<T: Storage>(self) -> Result<$name<T>, StorageError> {
+                let value = self.0.to_f64();
+                if S::from_f64(value).is_none_or(|back| back != self.0) {
+                    return Err(StorageError::NotRepresentable(value));
+                }
+
+                T::from_f64(value)
+                    .filter(|converted| converted.to_f64() == value)
+                    .map($name)
+                    .ok_or(StorageError::NotRepresentable(value))
+            }
+
+            pub fn $to_base(&self) -> f64 {
+                self.0.to_f64() / $per_base
+            }
+
+            /// Converts from the base unit. Float storage keeps the nearest
+            /// value, integer storage fails if it can't keep the exact value.
+            pub fn $from_base(value: f64) -> Result<Self, StorageError> {
+                let value = value * $per_base;
+                S::from_f64(value)
+                    .map(Self)
+                    .ok_or(StorageError::NotRepresentable(value))
+            }
+        }
+
+        /// Saturates on overflow. Use `checked_add()` to detect it instead.
+        impl<S: Storage> Add for $name<S> {
+            type Output = Self;
+
+            fn add(self, rhs: Self) -> Self::Output {
+                Self(self.0.saturating_add(rhs.0))
+            }
+        }
+
+        /// Saturates on overflow. Use `checked_sub()` to detect it instead.
+        impl<S: Storage> Sub for $name<S> {
+            type Output = Self;
+
+            fn sub(self, rhs: Self) -> Self::Output {
+                Self(self.0.saturating_sub(rhs.0))
+            }
+        }
+    };
+}
+
+quantity!(
+    /// Distance in millimeter, stored exactly as `u64` by default.
+    Millimeter,
+    to_meters,
+    from_meters,
+    1000.0
+);
+
+quantity!(
+    /// Time in milliseconds, stored exactly as `u64` by default.
+    Millisecond,
+    to_seconds,
+    from_seconds,
+    1000.0
+);
+
+/// Calculate the speed as meter per second.
+pub fn meter_per_second<S: Storage>(
+    distance: Millimeter<S>,
+    time: Millisecond<S>,
+) -> Result<f64, StorageError> {
+    let time = time.to_seconds();
+    if time == 0.0 {
+        return Err(StorageError::ZeroTime);
+    }
+
+    Ok(distance.to_meters() / time)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn integer_arithmetic() {
+        let distance = Millimeter::new(u64::MAX - 1);
+
+        assert_eq!(
+            distance.checked_add(Millimeter::new(1)),
+            Ok(Millimeter::new(u64::MAX))
+        );
+        assert_eq!(
+            distance.checked_add(Millimeter::new(2)),
+            Err(StorageError::Overflow)
+        );
+        assert_eq!(distance + Millimeter::new(2), Millimeter::new(u64::MAX));
+        assert_eq!(
+            Millimeter::<u64>::new(1) - Millimeter::new(2),
+            Millimeter::new(0)
+        );
+        assert_eq!(
+            Millisecond::<u32>::new(1).checked_sub(Millisecond::new(2)),
+            Err(StorageError::Overflow)
+        );
+    }
+
+    #[test]
+    fn float_arithmetic() {
+        let time = Millisecond::new(f32::MAX);
+
+        assert_eq!(
+            time.checked_add(Millisecond::new(f32::MAX)),
+            Err(StorageError::Overflow)
+        );
+        assert_eq!(time + Millisecond::new(f32::MAX), time);
+        assert_eq!(
+            Millisecond::new(1.5_f64) + Millisecond::new(2.0),
+            Millisecond::new(3.5)
+        );
+    }
+
+    #[test]
+    fn checked_casts() {
+        assert_eq!(
+            Millimeter::new(1500_u64).try_cast::<f32>(),
+            Ok(Millimeter::new(1500.0))
+        );
+        assert_eq!(
+            Millimeter::new(12.0_f64).try_cast::<u64>(),
+            Ok(Millimeter::new(12))
+        );
+        assert_eq!(
+            Millimeter::new(1.5_f64).try_cast::<u64>(),
+            Err(StorageError::NotRepresentable(1.5))
+        );
+        assert_eq!(
+            Millimeter::new(-1.0_f64).try_cast::<u32>(),
+            Err(StorageError::NotRepresentable(-1.0))
+        );
+        assert!(Millimeter::new(u64::MAX).try_cast::<u32>().is_err());
+        assert!(Millimeter::new(u64::MAX as f64).try_cast::<u64>().is_err());
+        // u64 values above 2^53 can't be stored exactly in f64.
+        assert!(Millimeter::new(u64::MAX - 1).try_cast::<f64>().is_err());
+        assert!(Millimeter::new(0.1_f64).try_cast::<f32>().is_err());
+    }
+
+    #[test]
+    fn base_units() {
+        let distance = Millimeter::<u64>::from_meters(2.5).unwrap();
+        assert_eq!(distance, Millimeter::new(2500));
+        assert_eq!(distance.to_meters(), 2.5);
+        assert_eq!(
+            Millisecond::<u64>::from_seconds(0.0005),
+            Err(StorageError::NotRepresentable(0.5))
+        );
+
+        // Floats store the nearest value instead.
+        assert_eq!(
+            Millimeter::<f32>::from_meters(0.1),
+            Ok(Millimeter::new(100.0))
+        );
+        assert_eq!(
+            Millisecond::<f32>::from_seconds(0.0001),
+            Ok(Millisecond::new(0.1))
+        );
+        assert_eq!(
+            Millisecond::<f32>::from_seconds(f64::MAX),
+            Err(StorageError::NotRepresentable(f64::INFINITY))
+        );
+
+        let speed = meter_per_second(Millimeter::new(10_000_u64), Millisecond::new(2_000));
+        assert_eq!(speed, Ok(5.0));
+        assert_eq!(
+            meter_per_second(Millimeter::new(10_u64), Millisecond::new(0)),
+            Err(StorageError::ZeroTime)
+        );
+    }
+}
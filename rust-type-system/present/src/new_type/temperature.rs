@@ -0,0 +1,202 @@
+//! Temperatures are affine: absolute temperatures are points on a scale,
+//! while differences between them are deltas.
+//!
+//! Subtracting two points gives a delta and a delta can be added to a point,
+//! but adding two points has no meaning and doesn't compile.
+
+use std::ops::{Add, Mul, Neg, Sub};
+
+/// Offset between the Celsius and Kelvin scales.
+const CELSIUS_OFFSET: f64 = 273.15;
+/// Offset between the Fahrenheit and Kelvin scales.
+const FAHRENHEIT_OFFSET: f64 = 459.67;
+/// Size of a degree Fahrenheit in kelvin.
+const FAHRENHEIT_DEGREE: f64 = 5.0 / 9.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+pub struct Celsius(pub f64);
+
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+pub struct Kelvin(pub f64);
+
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+pub struct Fahrenheit(pub f64);
+
+/// Difference between two temperatures, independent of their scale.
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+pub struct TemperatureDelta {
+    kelvin: f64,
+}
+
+impl TemperatureDelta {
+    /// Delta in kelvin, which is the same as degree Celsius.
+    pub fn kelvin(value: f64) -> Self {
+        Self { kelvin: value }
+    }
+
+    pub fn fahrenheit(value: f64) -> Self {
+        Self {
+            kelvin: value * FAHRENHEIT_DEGREE,
+        }
+    }
+
+    pub fn as_kelvin(&self) -> f64 {
+        self.kelvin
+    }
+
+    pub fn as_fahrenheit(&self) -> f64 {
+        self.kelvin / FAHRENHEIT_DEGREE
+    }
+}
+
+impl Add for TemperatureDelta {
+    type Output = TemperatureDelta;
+
+    fn add(self, rhs: TemperatureDelta) -> Self::Output {
+        TemperatureDelta::kelvin(self.kelvin + rhs.kelvin)
+    }
+}
+
+impl Sub for TemperatureDelta {
+    type Output = TemperatureDelta;
+
+    fn sub(self, rhs: TemperatureDelta) -> Self::Output {
+        TemperatureDelta::kelvin(self.kelvin - rhs.kelvin)
+    }
+}
+
+impl Neg for TemperatureDelta {
+    type Output = TemperatureDelta;
+
+    fn neg(self) -> Self::Output {
+        TemperatureDelta::kelvin(-self.kelvin)
+    }
+}
+
+impl Mul<f64> for TemperatureDelta {
+    type Output = TemperatureDelta;
+
+    fn mul(self, rhs: f64) -> Self::Output {
+        TemperatureDelta::kelvin(self.kelvin * rhs)
+    }
+}
+
+/// Implements the point operations for an absolute temperature scale.
+///
+/// * `$to_delta`: converts the scale difference into a delta.
+/// * `$from_delta`: converts a delta into the scale difference.
+macro_rules! temperature_point {
+    ($($scale:ident: $to_delta:ident, $from_delta:ident);*) => {$(
+        impl Sub for $scale {
+            type Output = TemperatureDelta;
+
+            fn sub(self, rhs: $scale) -> Self::Output {
+                TemperatureDelta::$to_delta(self.0 - rhs.0)
+            }
+        }
+
+        impl Add<TemperatureDelta> for $scale {
+            type Output = $scale;
+
+            fn add(self, rhs: TemperatureDelta) -> Self::Output {
+                $scale(self.0 + rhs.$from_delta())
+            }
+        }
+
+        impl Sub<TemperatureDelta> for $scale {
+            type Output = $scale;
+
+            fn sub(self, rhs: TemperatureDelta) -> Self::Output {
+                $scale(self.0 - rhs.$from_delta())
+            }
+        }
+    )*};
+}
+
+temperature_point!(
+    Celsius: kelvin, as_kelvin;
+    Kelvin: kelvin, as_kelvin;
+    Fahrenheit: fahrenheit, as_fahrenheit
+);
+
+impl From<Celsius> for Kelvin {
+    fn from(value: Celsius) -> Self {
+        Kelvin(value.0 + CELSIUS_OFFSET)
+    }
+}
+
+impl From<Kelvin> for Celsius {
+    fn from(value: Kelvin) -> Self {
+        Celsius(value.0 - CELSIUS_OFFSET)
+    }
+}
+
+impl From<Fahrenheit> for Kelvin {
+    fn from(value: Fahrenheit) -> Self {
+        Kelvin((value.0 + FAHRENHEIT_OFFSET) * FAHRENHEIT_DEGREE)
+    }
+}
+
+impl From<Kelvin> for Fahrenheit {
+    fn from(value: Kelvin) -> Self {
+        Fahrenheit(value.0 / FAHRENHEIT_DEGREE - FAHRENHEIT_OFFSET)
+    }
+}
+
+impl From<Celsius> for Fahrenheit {
+    fn from(value: Celsius) -> Self {
+        Kelvin::from(value).into()
+    }
+}
+
+impl From<Fahrenheit> for Celsius {
+    fn from(value: Fahrenheit) -> Self {
+        Kelvin::from(value).into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(left: f64, right: f64) {
+        assert!((left - right).abs() < 1e-9, "{left} != {right}");
+    }
+
+    #[test]
+    fn points_and_deltas() {
+        let delta = Celsius(30.0) - Celsius(20.0);
+        assert_eq!(delta, TemperatureDelta::kelvin(10.0));
+        assert_eq!(Celsius(20.0) + delta, Celsius(30.0));
+        assert_eq!(Kelvin(300.0) - delta, Kelvin(290.0));
+
+        // Compiler error. Yay!
+        // let sum = Celsius(20.0) + Celsius(30.0);
+    }
+
+    #[test]
+    fn fahrenheit_deltas() {
+        let delta = Fahrenheit(50.0) - Fahrenheit(32.0);
+        assert_close(delta.as_kelvin(), 10.0);
+        assert_close(delta.as_fahrenheit(), 18.0);
+
+        let warmer = Celsius(0.0) + TemperatureDelta::fahrenheit(9.0);
+        assert_close(warmer.0, 5.0);
+    }
+
+    #[test]
+    fn delta_arithmetic() {
+        let delta = TemperatureDelta::kelvin(4.0);
+        assert_eq!(delta + delta, TemperatureDelta::kelvin(8.0));
+        assert_eq!(delta - delta * 2.0, -delta);
+    }
+
+    #[test]
+    fn scale_conversions() {
+        assert_close(Kelvin::from(Celsius(0.0)).0, 273.15);
+        assert_close(Celsius::from(Kelvin(0.0)).0, -273.15);
+        assert_close(Fahrenheit::from(Celsius(100.0)).0, 212.0);
+        assert_close(Celsius::from(Fahrenheit(-40.0)).0, -40.0);
+        assert_close(Kelvin::from(Fahrenheit(32.0)).0, 273.15);
+    }
+}
@@ -49,26 +49,26 @@ mod bad {
     }
 }
 
-mod good {
+pub mod good {
 
     use std::marker::PhantomData;
 
-    struct OnGround;
-    struct InFlight;
+    pub struct OnGround;
+    pub struct InFlight;
 
-    struct Plane<T> {
+    pub struct Plane<T> {
         state: PhantomData<T>,
         seats_count: u16,
     }
 
     impl<T> Plane<T> {
-        fn get_seats_count(&self) -> u16 {
+        pub fn get_seats_count(&self) -> u16 {
             self.seats_count
         }
     }
 
     impl Plane<OnGround> {
-        fn new(seats: u16) -> Plane<OnGround> {
+        pub fn new(seats: u16) -> Plane<OnGround> {
             Plane {
                 state: PhantomData::<OnGround>,
                 seats_count: seats,
@@ -76,7 +76,7 @@ mod good {
         }
 
         #[must_use]
-        fn take_off(self) -> Plane<InFlight> {
+        pub fn take_off(self) -> Plane<InFlight> {
             Plane {
                 state: PhantomData::<InFlight>,
                 seats_count: self.seats_count,
@@ -86,7 +86,7 @@ mod good {
 
     impl Plane<InFlight> {
         #[must_use]
-        fn land(self) -> Plane<OnGround> {
+        pub fn land(self) -> Plane<OnGround> {
             Plane {
                 state: PhantomData::<OnGround>,
                 seats_count: self.seats_count,
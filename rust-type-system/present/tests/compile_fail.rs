@@ -0,0 +1,117 @@
+//! Ensures the misuses shown in the lessons are rejected by the compiler.
+//!
+//! Each snippet in `tests/compile_fail` is compiled against the `present`
+//! library with the local `rustc`, expecting the given error code.
+
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+/// Directory with the dependencies of the test binary, including the `present` library.
+fn deps_dir() -> PathBuf {
+    let exe = env::current_exe().expect("Test binary path must be known");
+    exe.parent()
+        .expect("Test binary must be in deps directory")
+        .to_path_buf()
+}
+
+/// Finds the most recently built rlib of the `present` library.
+fn present_rlib(deps: &Path) -> PathBuf {
+    fs::read_dir(deps)
+        .expect("Deps directory must be readable")
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.starts_with("libpresent-") && name.ends_with(".rlib"))
+        })
+        .max_by_key(|path| fs::metadata(path).and_then(|meta| meta.modified()).ok())
+        .expect("present library must be built before running the tests")
+}
+
+fn assert_compile_fail(snippet: &str, error_code: &str) {
+    let deps = deps_dir();
+    let snippet_path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/compile_fail")
+        .join(snippet);
+    let out_dir = env::temp_dir().join(format!(
+        "present-compile-fail-{}-{snippet}",
+        std::process::id()
+    ));
+    fs::create_dir_all(&out_dir).unwrap();
+
+    let rustc = env::var("RUSTC").unwrap_or_else(|_| String::from("rustc"));
+    let output = Command::new(rustc)
+        .args([
+            "--edition",
+            "2021",
+            "--crate-type",
+            "lib",
+            "--emit",
+            "metadata",
+        ])
+        .arg("--extern")
+        .arg(format!("present={}", present_rlib(&deps).display()))
+        .arg("-L")
+        .arg(format!("dependency={}", deps.display()))
+        .arg("--out-dir")
+        .arg(&out_dir)
+        .arg(&snippet_path)
+        .output()
+        .expect("rustc must be available");
+
+    let _ = fs::remove_dir_all(&out_dir);
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        !output.status.success(),
+        "Snippet '{snippet}' must not compile"
+    );
+    assert!(
+        stderr.contains(&format!("error[{error_code}]")),
+        "Snippet '{snippet}' must fail with {error_code}. Compiler output:\n{stderr}"
+    );
+}
+
+#[test]
+fn land_on_ground() {
+    assert_compile_fail("land_on_ground.rs", "E0599");
+}
+
+#[test]
+fn second_as_meter() {
+    assert_compile_fail("second_as_meter.rs", "E0308");
+}
+
+#[test]
+fn mixed_dimensions() {
+    assert_compile_fail("mixed_dimensions.rs", "E0308");
+}
+
+#[test]
+fn missing_field() {
+    assert_compile_fail("missing_field.rs", "E0027");
+}
+
+#[test]
+fn swapped_coordinates() {
+    assert_compile_fail("swapped_coordinates.rs", "E0308");
+}
+
+#[test]
+fn add_temperatures() {
+    assert_compile_fail("add_temperatures.rs", "E0308");
+}
+
+#[test]
+fn mixed_ids() {
+    assert_compile_fail("mixed_ids.rs", "E0308");
+}
+
+#[test]
+fn unbuilt_options() {
+    assert_compile_fail("unbuilt_options.rs", "E0308");
+}
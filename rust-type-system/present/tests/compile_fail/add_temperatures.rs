@@ -0,0 +1,6 @@
+// Absolute temperatures can't be added, only deltas can be added to them.
+use present::new_type::temperature::Celsius;
+
+pub fn example(morning: Celsius, evening: Celsius) {
+    let _sum = morning + evening;
+}
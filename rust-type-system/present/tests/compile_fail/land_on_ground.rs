@@ -0,0 +1,6 @@
+// A plane on the ground has no `land()` method.
+use present::state_pattern::good::{OnGround, Plane};
+
+pub fn example(plane: Plane<OnGround>) {
+    let _plane = plane.land();
+}
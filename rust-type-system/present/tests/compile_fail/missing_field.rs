@@ -0,0 +1,10 @@
+// Exhaustive destructuring must mention every field.
+use present::exhaustive_pattern::good::CliArgs;
+
+pub fn example(args: &CliArgs) {
+    let CliArgs {
+        input: _,
+        interval: _,
+        separtor: _,
+    } = args;
+}
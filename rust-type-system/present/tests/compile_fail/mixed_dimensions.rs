@@ -0,0 +1,6 @@
+// Quantities of different dimensions can't be added.
+use present::new_type::good::{Meter, Second};
+
+pub fn example(distance: Meter, time: Second) {
+    let _sum = distance + time;
+}
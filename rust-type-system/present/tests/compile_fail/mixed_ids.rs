@@ -0,0 +1,14 @@
+// Identifiers of different kinds can't be passed in each other's place.
+use present::new_type::id::{Id, IdTag};
+
+pub enum User {}
+impl IdTag for User {}
+
+pub enum Order {}
+impl IdTag for Order {}
+
+fn load_user(_id: Id<User>) {}
+
+pub fn example(order: Id<Order>) {
+    load_user(order);
+}
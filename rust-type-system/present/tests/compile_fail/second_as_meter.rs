@@ -0,0 +1,6 @@
+// Distance and time can't be swapped.
+use present::new_type::good::{Meter, Second, Speed};
+
+pub fn example(distance: Meter, time: Second) {
+    let _speed = Speed::new(time, distance);
+}
@@ -0,0 +1,6 @@
+// Latitude and longitude can't be swapped.
+use present::new_type::geo::{GeoPoint, Latitude, Longitude};
+
+pub fn example(latitude: Latitude, longitude: Longitude) {
+    let _point = GeoPoint::new(longitude, latitude);
+}
@@ -0,0 +1,9 @@
+// Queries only take options which passed `QueryOptions::build`.
+use present::enum_bool::{
+    backend::{DataCache, RestService},
+    good::{DataService, QueryOptions},
+};
+
+pub fn example<C: DataCache, R: RestService>(service: &DataService<C, R>) {
+    let _result = service.query_data(QueryOptions::default().limit(0));
+}
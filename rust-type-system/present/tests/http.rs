@@ -0,0 +1,241 @@
+//! Runs `DataService` against the local stand-in HTTP server.
+
+use std::{
+    collections::HashMap,
+    thread,
+    time::{Duration, Instant},
+};
+
+use present::enum_bool::{
+    backend::{Data, QueryError},
+    cache::InMemoryCache,
+    clock::MockClock,
+    good::{DataService, DataSource, EmptyDataOptions, QueryOptions, ValidQueryOptions, DATA_KEY},
+    http::{Fixture, Gate, HttpRestService, StandInServer},
+    replay::ReplayRestService,
+    retry::{RetryPolicy, RetryingRestService},
+};
+
+const DATA_FIXTURE: &str = include_str!("fixtures/data.json");
+
+fn start_service(fixture: Fixture) -> (StandInServer, DataService<InMemoryCache, HttpRestService>) {
+    let fixtures = HashMap::from([(DATA_KEY.to_owned(), fixture)]);
+    let server = StandInServer::start(fixtures).unwrap();
+    let rest_api = HttpRestService::new(server.addr()).with_timeout(Duration::from_millis(200));
+
+    (server, DataService::new(InMemoryCache::new(), rest_api))
+}
+
+fn api_options(empty: EmptyDataOptions) -> QueryOptions {
+    QueryOptions::default()
+        .source(DataSource::ApiService)
+        .empty(empty)
+}
+
+fn api(empty: EmptyDataOptions) -> ValidQueryOptions {
+    api_options(empty).build().unwrap()
+}
+
+fn ids(data: &[Data]) -> Vec<&str> {
+    data.iter().map(|item| item.id.as_str()).collect()
+}
+
+#[test]
+fn query_api() {
+    let (_server, service) = start_service(Fixture::json(DATA_FIXTURE));
+
+    let data = service
+        .query_data(api(EmptyDataOptions::Keep))
+        .unwrap()
+        .data;
+    assert_eq!(ids(&data), ["sensor-1", "sensor-2", "sensor-3"]);
+    assert_eq!(data[0], Data::new("sensor-1", "21.5"));
+
+    let data = service
+        .query_data(api(EmptyDataOptions::Ignore))
+        .unwrap()
+        .data;
+    assert_eq!(ids(&data), ["sensor-1", "sensor-3"]);
+}
+
+#[test]
+fn http_errors() {
+    let (_server, service) = start_service(Fixture::status(500));
+    assert_eq!(
+        service.query_data(api(EmptyDataOptions::Keep)),
+        Err(QueryError::Http(500))
+    );
+
+    let (_server, service) = start_service(Fixture::json("not json"));
+    assert!(matches!(
+        service.query_data(api(EmptyDataOptions::Keep)),
+        Err(QueryError::InvalidResponse(_))
+    ));
+}
+
+#[test]
+fn unknown_path() {
+    let server = StandInServer::start(HashMap::new()).unwrap();
+    let service = DataService::new(InMemoryCache::new(), HttpRestService::new(server.addr()));
+
+    assert_eq!(
+        service.query_data(api(EmptyDataOptions::Keep)),
+        Err(QueryError::NotFound(DATA_KEY.into()))
+    );
+}
+
+#[test]
+fn timeout() {
+    let fixture = Fixture::json(DATA_FIXTURE).with_delay(Duration::from_secs(1));
+    let (_server, service) = start_service(fixture);
+
+    assert_eq!(
+        service.query_data(api(EmptyDataOptions::Keep)),
+        Err(QueryError::Timeout)
+    );
+}
+
+#[test]
+fn timeout_option() {
+    let fixture = Fixture::json(DATA_FIXTURE).with_delay(Duration::from_millis(400));
+    let (_server, service) = start_service(fixture);
+
+    let options = api_options(EmptyDataOptions::Keep)
+        .timeout(Duration::from_secs(5))
+        .build()
+        .unwrap();
+    assert_eq!(service.query_data(options).unwrap().data.len(), 3);
+    let options = api_options(EmptyDataOptions::Keep)
+        .timeout(Duration::from_millis(50))
+        .build()
+        .unwrap();
+    assert_eq!(service.query_data(options), Err(QueryError::Timeout));
+}
+
+#[test]
+fn retry_flaky_api() {
+    let fixtures = HashMap::from([(
+        DATA_KEY.to_owned(),
+        vec![Fixture::status(503), Fixture::json(DATA_FIXTURE)],
+    )]);
+    let server = StandInServer::start_sequences(fixtures).unwrap();
+    let rest_api = RetryingRestService::with_clock(
+        HttpRestService::new(server.addr()),
+        RetryPolicy::default(),
+        MockClock::new(),
+    );
+    let service = DataService::new(InMemoryCache::new(), rest_api);
+
+    let data = service
+        .query_data(api(EmptyDataOptions::Keep))
+        .unwrap()
+        .data;
+    assert_eq!(data.len(), 3);
+    assert_eq!(server.request_count(DATA_KEY), 2);
+}
+
+/// Options for queries waiting on a gated fixture, without timing out
+/// before the test opens the gate.
+fn gated(empty: EmptyDataOptions) -> ValidQueryOptions {
+    api_options(empty)
+        .timeout(Duration::from_secs(30))
+        .build()
+        .unwrap()
+}
+
+/// Waits until the condition holds, failing after some seconds.
+fn wait_until(condition: impl Fn() -> bool) {
+    let deadline = Instant::now() + Duration::from_secs(10);
+    while !condition() {
+        assert!(Instant::now() < deadline, "condition not met in time");
+        thread::sleep(Duration::from_millis(1));
+    }
+}
+
+#[test]
+fn coalesce_concurrent_queries() {
+    let gate = Gate::new();
+    let (server, service) = start_service(Fixture::json(DATA_FIXTURE).with_gate(gate.clone()));
+    let threads = 8;
+
+    let results: Vec<_> = thread::scope(|scope| {
+        let handles: Vec<_> = (0..threads)
+            .map(|_| scope.spawn(|| service.query_data(gated(EmptyDataOptions::Ignore))))
+            .collect();
+        wait_until(|| service.metrics().snapshot().coalesced_queries == threads - 1);
+        gate.open();
+        handles
+            .into_iter()
+            .map(|handle| handle.join().unwrap())
+            .collect()
+    });
+
+    assert_eq!(server.request_count(DATA_KEY), 1);
+    for result in results {
+        assert_eq!(ids(&result.unwrap().data), ["sensor-1", "sensor-3"]);
+    }
+}
+
+#[test]
+fn coalesce_errors() {
+    let gate = Gate::new();
+    let (server, service) = start_service(Fixture::status(503).with_gate(gate.clone()));
+
+    thread::scope(|scope| {
+        for _ in 0..4 {
+            scope.spawn(|| {
+                assert_eq!(
+                    service.query_data(gated(EmptyDataOptions::Keep)),
+                    Err(QueryError::Http(503))
+                );
+            });
+        }
+        wait_until(|| service.metrics().snapshot().coalesced_queries == 3);
+        gate.open();
+    });
+    assert_eq!(server.request_count(DATA_KEY), 1);
+}
+
+#[test]
+fn coalesce_only_same_timeout() {
+    let gate = Gate::new();
+    let (server, service) = start_service(Fixture::json(DATA_FIXTURE).with_gate(gate.clone()));
+
+    thread::scope(|scope| {
+        let slow = scope.spawn(|| service.query_data(gated(EmptyDataOptions::Keep)));
+        wait_until(|| server.request_count(DATA_KEY) == 1);
+
+        // Joining the running fetch would wait for it beyond the timeout.
+        let options = api_options(EmptyDataOptions::Keep)
+            .timeout(Duration::from_millis(50))
+            .build()
+            .unwrap();
+        assert_eq!(service.query_data(options), Err(QueryError::Timeout));
+        assert_eq!(service.metrics().snapshot().coalesced_queries, 0);
+
+        gate.open();
+        assert_eq!(slow.join().unwrap().unwrap().data.len(), 3);
+    });
+    assert_eq!(server.request_count(DATA_KEY), 2);
+}
+
+#[test]
+fn replay_recorded_api() {
+    let fixture = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/fixtures/recorded_api.json"
+    );
+    let rest_api = ReplayRestService::open(fixture).unwrap();
+    let service = DataService::new(InMemoryCache::new(), rest_api);
+
+    let data = service
+        .query_data(api(EmptyDataOptions::Ignore))
+        .unwrap()
+        .data;
+    assert_eq!(ids(&data), ["sensor-1", "sensor-3"]);
+
+    let options = api(EmptyDataOptions::Keep);
+    let items: Vec<_> = service.query_stream(options).collect();
+    assert_eq!(items.len(), 3);
+    assert_eq!(items[2], Err(QueryError::Http(503)));
+}